@@ -0,0 +1,40 @@
+use std::sync::Arc;
+
+use crate::{hittable::{HitRecord, Hittable}, interval::Interval, ray::Ray, texture::{Texture, TextureContext}, utils::random_float};
+
+/// Wraps any hittable and cuts its surface away wherever the `alpha` texture, read through
+/// `Texture::scalar`, is 0, so rays carry on to whatever lies behind, as for leaves, fences and
+/// decals. Alpha in between is coverage: the surface is there for that fraction of the rays.
+pub struct AlphaCutout {
+    object: Box<dyn Hittable>,
+    alpha: Arc<dyn Texture>,
+}
+
+impl AlphaCutout {
+    pub fn new(object: Box<dyn Hittable>, alpha: Arc<dyn Texture>) -> Self {
+        Self { object, alpha }
+    }
+
+    fn covers(&self, hit_record: &HitRecord) -> bool {
+        let alpha = self.alpha.scalar(&TextureContext::from_hit(hit_record));
+        alpha >= 1.0 || (alpha > 0.0 && random_float() < alpha)
+    }
+}
+
+impl Hittable for AlphaCutout {
+    fn hit(&self, ray: &Ray, interval: Interval) -> Option<HitRecord> {
+        let mut interval = interval;
+        loop {
+            let hit_record = self.object.hit(ray, interval)?;
+            if self.covers(&hit_record) {
+                return Some(hit_record);
+            }
+            interval.min = hit_record.t;
+        }
+    }
+
+    fn occluded(&self, ray: &Ray, interval: Interval) -> bool {
+        // The cutout needs the hit's (u, v), so there is no cheaper test than a full hit.
+        self.hit(ray, interval).is_some()
+    }
+}
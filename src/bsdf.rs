@@ -0,0 +1,932 @@
+use std::{f64::consts::PI, ops::BitOr};
+
+use crate::{color::Color, fresnel::{fr_dielectric, ComplexIor, FilmLayer, Fresnel}, hittable::HitRecord, microfacet::TrowbridgeReitz, sampling::{power_heuristic, sample_cosine_hemisphere, sample_uniform_sphere}, utils::random_float, vec3::{self, cross, dot, unit_vector, Vec3}};
+
+/// Kinds of scattering a BSDF or one of its samples involves.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct BsdfFlags(u8);
+
+impl BsdfFlags {
+    pub const REFLECTION: Self = Self(1);
+    pub const TRANSMISSION: Self = Self(2);
+    pub const DIFFUSE: Self = Self(4);
+    pub const GLOSSY: Self = Self(8);
+    /// A delta lobe: `eval` and `pdf` are zero for it, it can only be sampled.
+    pub const SPECULAR: Self = Self(16);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_specular(self) -> bool {
+        self.contains(Self::SPECULAR)
+    }
+
+    /// Whether any part is diffuse or glossy, i.e. can be evaluated for a given direction.
+    pub fn is_non_specular(self) -> bool {
+        self.0 & (Self::DIFFUSE.0 | Self::GLOSSY.0) != 0
+    }
+
+    pub fn is_transmission(self) -> bool {
+        self.contains(Self::TRANSMISSION)
+    }
+}
+
+impl BitOr for BsdfFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// A sampled direction. For delta lobes `f` and `pdf` are both scaled by the same delta, so the usual
+/// `f * |cos θi| / pdf` still gives the path weight.
+pub struct BsdfSample {
+    pub f: Color,
+    pub wi: Vec3,
+    pub pdf: f64,
+    pub flags: BsdfFlags,
+    /// Relative index of refraction η_t / η_i crossed by a transmitted sample, 1 otherwise.
+    pub eta: f64,
+}
+
+/// The scattering function itself, in a local shading frame: +z is the outward shading normal and
+/// +x follows dpdu. Both `wo` and `wi` point away from the surface.
+pub trait Bxdf {
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Color;
+
+    /// Samples `wi` given `wo`; `u[0]` is meant for picking a lobe, `u[1]` and `u[2]` for the
+    /// direction. `None` when the sample is absorbed.
+    fn sample(&self, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample>;
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> f64;
+
+    fn flags(&self) -> BsdfFlags;
+}
+
+/// Orthonormal basis for moving directions in and out of the local shading frame.
+#[derive(Clone, Copy)]
+pub struct Frame {
+    x: Vec3,
+    y: Vec3,
+    z: Vec3,
+}
+
+impl Frame {
+    /// Frame around `z` with `x` along the part of `tangent` orthogonal to it, or an arbitrary
+    /// perpendicular when `tangent` is degenerate.
+    pub fn from_z_and_tangent(z: Vec3, tangent: Vec3) -> Self {
+        let mut x = tangent - dot(&tangent, &z) * z;
+        if x.near_zero() {
+            // No usable parameterization here (e.g. a sphere pole); any tangent beats a NaN frame.
+            let helper = if z.x().abs() > 0.9 { Vec3::with_values(0.0, 1.0, 0.0) } else { Vec3::with_values(1.0, 0.0, 0.0) };
+            x = cross(&helper, &z);
+        }
+        let x = unit_vector(&x);
+        Self { x, y: cross(&z, &x), z }
+    }
+
+    pub fn to_local(self, v: Vec3) -> Vec3 {
+        Vec3::with_values(dot(&v, &self.x), dot(&v, &self.y), dot(&v, &self.z))
+    }
+
+    pub fn to_world(self, v: Vec3) -> Vec3 {
+        v.x() * self.x + v.y() * self.y + v.z() * self.z
+    }
+}
+
+/// A `Bxdf` placed at a hit point: converts world-space directions to the shading frame, and rejects
+/// directions for which the shading and geometric normals disagree on whether light is reflected or
+/// transmitted, so bent shading normals can't leak light through the surface.
+pub struct Bsdf {
+    frame: Frame,
+    geometric_normal: Vec3,
+    bxdf: Box<dyn Bxdf>,
+}
+
+impl Bsdf {
+    pub fn new(hit_record: &HitRecord, bxdf: impl Bxdf + 'static) -> Self {
+        let geometric_normal = if hit_record.front_face { hit_record.normal } else { -hit_record.normal };
+        Self {
+            frame: Frame::from_z_and_tangent(hit_record.outward_shading_normal(), hit_record.dpdu),
+            geometric_normal,
+            bxdf: Box::new(bxdf),
+        }
+    }
+
+    /// The same BSDF with its BxDF replaced by `f` of it, in the same shading frame, such as a coat
+    /// over another material's scattering.
+    pub fn map_bxdf<B: Bxdf + 'static>(self, f: impl FnOnce(Box<dyn Bxdf>) -> B) -> Self {
+        Self { bxdf: Box::new(f(self.bxdf)), ..self }
+    }
+
+    pub fn eval(&self, wo: Vec3, wi: Vec3) -> Color {
+        let (wo_local, wi_local) = (self.frame.to_local(wo), self.frame.to_local(wi));
+        if !self.consistent(wo, wi, wo_local, wi_local) {
+            return Color::new();
+        }
+        self.bxdf.eval(wo_local, wi_local)
+    }
+
+    pub fn sample(&self, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        let wo_local = self.frame.to_local(wo);
+        if wo_local.z() == 0.0 {
+            return None;
+        }
+
+        let mut sample = self.bxdf.sample(wo_local, u)?;
+        if sample.pdf == 0.0 || sample.wi.z() == 0.0 {
+            return None;
+        }
+
+        let wi = self.frame.to_world(sample.wi);
+        if !self.consistent(wo, wi, wo_local, sample.wi) {
+            return None;
+        }
+        sample.wi = wi;
+        Some(sample)
+    }
+
+    pub fn pdf(&self, wo: Vec3, wi: Vec3) -> f64 {
+        let (wo_local, wi_local) = (self.frame.to_local(wo), self.frame.to_local(wi));
+        if !self.consistent(wo, wi, wo_local, wi_local) {
+            return 0.0;
+        }
+        self.bxdf.pdf(wo_local, wi_local)
+    }
+
+    pub fn flags(&self) -> BsdfFlags {
+        self.bxdf.flags()
+    }
+
+    fn consistent(&self, wo: Vec3, wi: Vec3, wo_local: Vec3, wi_local: Vec3) -> bool {
+        let geometric_reflection = dot(&wo, &self.geometric_normal) * dot(&wi, &self.geometric_normal) > 0.0;
+        let shading_reflection = wo_local.z() * wi_local.z() > 0.0;
+        geometric_reflection == shading_reflection
+    }
+}
+
+pub fn same_hemisphere(w: Vec3, wp: Vec3) -> bool {
+    w.z() * wp.z() > 0.0
+}
+
+/// Mirror direction of `wo` about the local +z axis.
+pub fn reflect_local(wo: Vec3) -> Vec3 {
+    Vec3::with_values(-wo.x(), -wo.y(), wo.z())
+}
+
+/// A weighted sum of lobes. Sampling picks one lobe in proportion to its sampling weight, then
+/// evaluates the whole mixture in the chosen direction, unless the lobe was a delta one.
+#[derive(Default)]
+pub struct MixtureBxdf {
+    lobes: Vec<Lobe>,
+}
+
+struct Lobe {
+    scale: Color,
+    sampling_weight: f64,
+    bxdf: Box<dyn Bxdf>,
+}
+
+impl MixtureBxdf {
+    /// Adds `bxdf` scaled by `scale`. Lobes with no sampling weight are left out entirely, so the
+    /// weight should be positive whenever the lobe contributes.
+    pub fn add(&mut self, scale: Color, sampling_weight: f64, bxdf: impl Bxdf + 'static) {
+        if sampling_weight > 0.0 {
+            self.lobes.push(Lobe { scale, sampling_weight, bxdf: Box::new(bxdf) });
+        }
+    }
+
+    fn total_weight(&self) -> f64 {
+        self.lobes.iter().map(|lobe| lobe.sampling_weight).sum()
+    }
+}
+
+impl Bxdf for MixtureBxdf {
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Color {
+        let mut f = Color::new();
+        for lobe in &self.lobes {
+            f += lobe.scale * lobe.bxdf.eval(wo, wi);
+        }
+        f
+    }
+
+    fn sample(&self, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        let total = self.total_weight();
+        let mut target = u[0] * total;
+        let index = self.lobes.iter().position(|lobe| {
+            if target < lobe.sampling_weight {
+                return true;
+            }
+            target -= lobe.sampling_weight;
+            false
+        });
+        let lobe = &self.lobes[index.unwrap_or(self.lobes.len().checked_sub(1)?)];
+
+        // Reuse what is left of u[0] within the chosen lobe's share.
+        let remapped = f64::min(target / lobe.sampling_weight, 1.0 - f64::EPSILON).max(0.0);
+        let mut sample = lobe.bxdf.sample(wo, [remapped, u[1], u[2]])?;
+        if sample.flags.is_specular() {
+            sample.f = lobe.scale * sample.f;
+            sample.pdf *= lobe.sampling_weight / total;
+            return Some(sample);
+        }
+
+        sample.f = self.eval(wo, sample.wi);
+        sample.pdf = self.pdf(wo, sample.wi);
+        (sample.pdf > 0.0).then_some(sample)
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> f64 {
+        let total = self.total_weight();
+        self.lobes.iter().map(|lobe| lobe.sampling_weight / total * lobe.bxdf.pdf(wo, wi)).sum()
+    }
+
+    fn flags(&self) -> BsdfFlags {
+        self.lobes.iter().fold(BsdfFlags(0), |flags, lobe| flags | lobe.bxdf.flags())
+    }
+}
+
+/// Lambertian reflection, the same on both sides of the surface.
+pub struct DiffuseBxdf {
+    reflectance: Color,
+}
+
+impl DiffuseBxdf {
+    pub fn new(reflectance: Color) -> Self {
+        Self { reflectance }
+    }
+}
+
+impl Bxdf for DiffuseBxdf {
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Color {
+        if !same_hemisphere(wo, wi) {
+            return Color::new();
+        }
+        self.reflectance / PI
+    }
+
+    fn sample(&self, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        let mut wi = sample_cosine_hemisphere((u[1], u[2]));
+        if wo.z() < 0.0 {
+            wi = Vec3::with_values(wi.x(), wi.y(), -wi.z());
+        }
+
+        Some(BsdfSample {
+            f: self.reflectance / PI,
+            wi,
+            pdf: wi.z().abs() / PI,
+            flags: BsdfFlags::REFLECTION | BsdfFlags::DIFFUSE,
+            eta: 1.0,
+        })
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> f64 {
+        if !same_hemisphere(wo, wi) {
+            return 0.0;
+        }
+        wi.z().abs() / PI
+    }
+
+    fn flags(&self) -> BsdfFlags {
+        BsdfFlags::REFLECTION | BsdfFlags::DIFFUSE
+    }
+}
+
+/// The classic fuzzy metal: the mirror direction is pushed to a uniformly random point on a sphere of
+/// radius `fuzz` around its tip. Samples that end up below the surface are absorbed. A fuzz of zero is
+/// a perfect mirror.
+pub struct FuzzyMetalBxdf {
+    albedo: Color,
+    fuzz: f64,
+    film: Option<(FilmLayer, ComplexIor)>,
+}
+
+impl FuzzyMetalBxdf {
+    pub fn new(albedo: Color, fuzz: f64) -> Self {
+        Self { albedo, fuzz, film: None }
+    }
+
+    /// Fuzzy metal under a thin film. The albedo is taken as the metal's reflectance at normal
+    /// incidence and replaced by the film's reflectance over that metal as seen from `wo`.
+    pub fn with_thin_film(albedo: Color, fuzz: f64, film: FilmLayer) -> Self {
+        Self { albedo, fuzz, film: Some((film, ComplexIor::from_reflectance(albedo))) }
+    }
+
+    fn albedo(&self, wo: Vec3) -> Color {
+        match &self.film {
+            Some((film, ior)) => film.reflectance_over_conductor(wo.z().abs(), ior),
+            None => self.albedo,
+        }
+    }
+
+    /// Solid-angle density of `wi`: every point where the ray along `wi` crosses the fuzz sphere
+    /// contributes its area density, 1 / (4π fuzz²), times t² / |cos| at that point.
+    fn fuzz_pdf(&self, wo: Vec3, wi: Vec3) -> f64 {
+        let b = dot(&wi, &reflect_local(wo));
+        let discriminant = b * b - (1.0 - self.fuzz * self.fuzz);
+        if discriminant <= 0.0 {
+            return 0.0;
+        }
+
+        let root = discriminant.sqrt();
+        [b - root, b + root]
+            .into_iter()
+            .filter(|&t| t > 0.0)
+            .map(|t| t * t / (4.0 * PI * self.fuzz * root))
+            .sum()
+    }
+}
+
+impl Bxdf for FuzzyMetalBxdf {
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Color {
+        if self.fuzz == 0.0 || !same_hemisphere(wo, wi) {
+            return Color::new();
+        }
+        self.albedo(wo) * (self.fuzz_pdf(wo, wi) / wi.z().abs())
+    }
+
+    fn sample(&self, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        let reflected = reflect_local(wo);
+        if self.fuzz == 0.0 {
+            return Some(BsdfSample {
+                f: self.albedo(wo) / reflected.z().abs(),
+                wi: reflected,
+                pdf: 1.0,
+                flags: BsdfFlags::REFLECTION | BsdfFlags::SPECULAR,
+                eta: 1.0,
+            });
+        }
+
+        let wi = reflected + self.fuzz * sample_uniform_sphere((u[1], u[2]));
+        if wi.near_zero() {
+            return None;
+        }
+        let wi = unit_vector(&wi);
+        if !same_hemisphere(wo, wi) {
+            return None;
+        }
+
+        let pdf = self.fuzz_pdf(wo, wi);
+        Some(BsdfSample {
+            f: self.albedo(wo) * (pdf / wi.z().abs()),
+            wi,
+            pdf,
+            flags: BsdfFlags::REFLECTION | BsdfFlags::GLOSSY,
+            eta: 1.0,
+        })
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> f64 {
+        if self.fuzz == 0.0 || !same_hemisphere(wo, wi) {
+            return 0.0;
+        }
+        self.fuzz_pdf(wo, wi)
+    }
+
+    fn flags(&self) -> BsdfFlags {
+        if self.fuzz == 0.0 {
+            BsdfFlags::REFLECTION | BsdfFlags::SPECULAR
+        } else {
+            BsdfFlags::REFLECTION | BsdfFlags::GLOSSY
+        }
+    }
+}
+
+/// Reflection off an opaque Trowbridge-Reitz microfacet surface, such as a conductor, with the
+/// Fresnel term given separately. Falls back to a perfect mirror when the distribution is effectively
+/// smooth.
+pub struct MicrofacetReflectionBxdf {
+    distribution: TrowbridgeReitz,
+    fresnel: Fresnel,
+}
+
+impl MicrofacetReflectionBxdf {
+    pub fn new(distribution: TrowbridgeReitz, fresnel: Fresnel) -> Self {
+        Self { distribution, fresnel }
+    }
+
+    /// Half vector of a reflection pair, on the +z side.
+    fn half_vector(wo: Vec3, wi: Vec3) -> Option<Vec3> {
+        let wm = wo + wi;
+        if wm.length_squared() == 0.0 {
+            return None;
+        }
+        let wm = unit_vector(&wm);
+        Some(if wm.z() < 0.0 { -wm } else { wm })
+    }
+}
+
+impl Bxdf for MicrofacetReflectionBxdf {
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Color {
+        if !same_hemisphere(wo, wi) || self.distribution.effectively_smooth() {
+            return Color::new();
+        }
+
+        let (cos_theta_o, cos_theta_i) = (wo.z().abs(), wi.z().abs());
+        let Some(wm) = Self::half_vector(wo, wi) else {
+            return Color::new();
+        };
+
+        let fresnel = self.fresnel.reflectance(dot(&wo, &wm).abs());
+        fresnel * (self.distribution.d(wm) * self.distribution.g(wo, wi) / (4.0 * cos_theta_i * cos_theta_o))
+    }
+
+    fn sample(&self, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        if self.distribution.effectively_smooth() {
+            let wi = reflect_local(wo);
+            let cos_theta_i = wi.z().abs();
+            return Some(BsdfSample {
+                f: self.fresnel.reflectance(cos_theta_i) / cos_theta_i,
+                wi,
+                pdf: 1.0,
+                flags: BsdfFlags::REFLECTION | BsdfFlags::SPECULAR,
+                eta: 1.0,
+            });
+        }
+
+        let wm = self.distribution.sample_wm(wo, (u[1], u[2]));
+        let wi = vec3::reflect(-wo, wm);
+        if !same_hemisphere(wo, wi) {
+            return None;
+        }
+
+        let pdf = self.distribution.d_visible(wo, wm) / (4.0 * dot(&wo, &wm).abs());
+        let (cos_theta_o, cos_theta_i) = (wo.z().abs(), wi.z().abs());
+        let fresnel = self.fresnel.reflectance(dot(&wo, &wm).abs());
+        Some(BsdfSample {
+            f: fresnel * (self.distribution.d(wm) * self.distribution.g(wo, wi) / (4.0 * cos_theta_i * cos_theta_o)),
+            wi,
+            pdf,
+            flags: BsdfFlags::REFLECTION | BsdfFlags::GLOSSY,
+            eta: 1.0,
+        })
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> f64 {
+        if !same_hemisphere(wo, wi) || self.distribution.effectively_smooth() {
+            return 0.0;
+        }
+
+        let Some(wm) = Self::half_vector(wo, wi) else {
+            return 0.0;
+        };
+        self.distribution.d_visible(wo, wm) / (4.0 * dot(&wo, &wm).abs())
+    }
+
+    fn flags(&self) -> BsdfFlags {
+        if self.distribution.effectively_smooth() {
+            BsdfFlags::REFLECTION | BsdfFlags::SPECULAR
+        } else {
+            BsdfFlags::REFLECTION | BsdfFlags::GLOSSY
+        }
+    }
+}
+
+/// Smooth glass: delta reflection and transmission, chosen by the exact Fresnel reflectance, so
+/// `RoughDielectricBxdf` meets it continuously as its roughness goes to zero.
+/// `index_of_refraction` is the inside of the surface relative to the outside (+z).
+pub struct DielectricBxdf {
+    index_of_refraction: f64,
+    film: Option<FilmLayer>,
+}
+
+impl DielectricBxdf {
+    pub fn new(index_of_refraction: f64) -> Self {
+        Self { index_of_refraction, film: None }
+    }
+
+    /// Smooth glass under a thin film on its outer side. The film's interference colors the
+    /// reflectance, and the transmittance with its complement.
+    pub fn with_thin_film(index_of_refraction: f64, film: FilmLayer) -> Self {
+        Self { index_of_refraction, film: Some(film) }
+    }
+}
+
+impl Bxdf for DielectricBxdf {
+    fn eval(&self, _wo: Vec3, _wi: Vec3) -> Color {
+        Color::new()
+    }
+
+    fn sample(&self, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        let white = Color::with_values(1.0, 1.0, 1.0);
+        let reflectance = match self.film {
+            Some(film) => film.reflectance_over_dielectric(wo.z(), self.index_of_refraction),
+            None => fr_dielectric(wo.z(), self.index_of_refraction) * white,
+        };
+        // Reflection is chosen by the mean over the channels, which is exact without a film.
+        let pr = (reflectance.x() + reflectance.y() + reflectance.z()) / 3.0;
+        if u[0] < pr {
+            let wi = reflect_local(wo);
+            return Some(BsdfSample {
+                f: reflectance / wi.z().abs(),
+                wi,
+                pdf: pr,
+                flags: BsdfFlags::REFLECTION | BsdfFlags::SPECULAR,
+                eta: 1.0,
+            });
+        }
+
+        let (wi, etap) = refract(wo, Vec3::with_values(0.0, 0.0, 1.0), self.index_of_refraction)?;
+        let transmittance = white - reflectance;
+        Some(BsdfSample {
+            f: transmittance / wi.z().abs(),
+            wi,
+            pdf: 1.0 - pr,
+            flags: BsdfFlags::TRANSMISSION | BsdfFlags::SPECULAR,
+            eta: etap,
+        })
+    }
+
+    fn pdf(&self, _wo: Vec3, _wi: Vec3) -> f64 {
+        0.0
+    }
+
+    fn flags(&self) -> BsdfFlags {
+        BsdfFlags::REFLECTION | BsdfFlags::TRANSMISSION | BsdfFlags::SPECULAR
+    }
+}
+
+/// Glass with a Trowbridge-Reitz microfacet surface, reflecting and refracting through the sampled
+/// microfacet normals (Walter et al. 2007). `index_of_refraction` has the same meaning as for
+/// `DielectricBxdf`. Like it, transmitted radiance isn't scaled by 1/η², which cancels out for closed
+/// objects anyway.
+pub struct RoughDielectricBxdf {
+    index_of_refraction: f64,
+    distribution: TrowbridgeReitz,
+}
+
+impl RoughDielectricBxdf {
+    pub fn new(index_of_refraction: f64, distribution: TrowbridgeReitz) -> Self {
+        Self { index_of_refraction, distribution }
+    }
+
+    /// The generalized half vector of a reflection or refraction pair, on the +z side, with the
+    /// relative index η_t / η_i of the path (1 for reflection). `None` for degenerate pairs and for
+    /// pairs only a back-facing microfacet could connect.
+    fn half_vector(&self, wo: Vec3, wi: Vec3) -> Option<(Vec3, f64)> {
+        let (cos_theta_o, cos_theta_i) = (wo.z(), wi.z());
+        let etap = if cos_theta_o * cos_theta_i > 0.0 {
+            1.0
+        } else if cos_theta_o > 0.0 {
+            self.index_of_refraction
+        } else {
+            1.0 / self.index_of_refraction
+        };
+
+        let wm = etap * wi + wo;
+        if cos_theta_i == 0.0 || cos_theta_o == 0.0 || wm.length_squared() == 0.0 {
+            return None;
+        }
+        let wm = unit_vector(&wm);
+        let wm = if wm.z() < 0.0 { -wm } else { wm };
+
+        if dot(&wm, &wi) * cos_theta_i < 0.0 || dot(&wm, &wo) * cos_theta_o < 0.0 {
+            return None;
+        }
+        Some((wm, etap))
+    }
+}
+
+impl Bxdf for RoughDielectricBxdf {
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Color {
+        if self.distribution.effectively_smooth() {
+            return Color::new();
+        }
+        let Some((wm, etap)) = self.half_vector(wo, wi) else {
+            return Color::new();
+        };
+
+        let (cos_theta_o, cos_theta_i) = (wo.z(), wi.z());
+        let reflectance = fr_dielectric(dot(&wo, &wm), self.index_of_refraction);
+        let d = self.distribution.d(wm);
+        let g = self.distribution.g(wo, wi);
+        let value = if etap == 1.0 {
+            d * g * reflectance / (4.0 * cos_theta_i * cos_theta_o).abs()
+        } else {
+            let denom = (dot(&wi, &wm) + dot(&wo, &wm) / etap).powi(2) * cos_theta_i * cos_theta_o;
+            d * (1.0 - reflectance) * g * (dot(&wi, &wm) * dot(&wo, &wm) / denom).abs()
+        };
+        Color::with_values(value, value, value)
+    }
+
+    fn sample(&self, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        if self.distribution.effectively_smooth() {
+            return DielectricBxdf::new(self.index_of_refraction).sample(wo, u);
+        }
+
+        let wm = self.distribution.sample_wm(wo, (u[1], u[2]));
+        let reflectance = fr_dielectric(dot(&wo, &wm), self.index_of_refraction);
+        let transmittance = 1.0 - reflectance;
+        let (cos_theta_o, visible) = (wo.z(), self.distribution.d_visible(wo, wm));
+
+        if u[0] < reflectance {
+            let wi = vec3::reflect(-wo, wm);
+            if !same_hemisphere(wo, wi) {
+                return None;
+            }
+
+            let value = self.distribution.d(wm) * self.distribution.g(wo, wi) * reflectance
+                / (4.0 * wi.z() * cos_theta_o).abs();
+            return Some(BsdfSample {
+                f: Color::with_values(value, value, value),
+                wi,
+                pdf: visible / (4.0 * dot(&wo, &wm).abs()) * reflectance,
+                flags: BsdfFlags::REFLECTION | BsdfFlags::GLOSSY,
+                eta: 1.0,
+            });
+        }
+
+        let (wi, etap) = refract(wo, wm, self.index_of_refraction)?;
+        if same_hemisphere(wo, wi) || wi.z() == 0.0 {
+            return None;
+        }
+
+        let denom = (dot(&wi, &wm) + dot(&wo, &wm) / etap).powi(2);
+        let dwm_dwi = dot(&wi, &wm).abs() / denom;
+        let value = transmittance * self.distribution.d(wm) * self.distribution.g(wo, wi)
+            * (dot(&wi, &wm) * dot(&wo, &wm) / (wi.z() * cos_theta_o * denom)).abs();
+        Some(BsdfSample {
+            f: Color::with_values(value, value, value),
+            wi,
+            pdf: visible * dwm_dwi * transmittance,
+            flags: BsdfFlags::TRANSMISSION | BsdfFlags::GLOSSY,
+            eta: etap,
+        })
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> f64 {
+        if self.distribution.effectively_smooth() {
+            return 0.0;
+        }
+        let Some((wm, etap)) = self.half_vector(wo, wi) else {
+            return 0.0;
+        };
+
+        let reflectance = fr_dielectric(dot(&wo, &wm), self.index_of_refraction);
+        let visible = self.distribution.d_visible(wo, wm);
+        if etap == 1.0 {
+            visible / (4.0 * dot(&wo, &wm).abs()) * reflectance
+        } else {
+            let denom = (dot(&wi, &wm) + dot(&wo, &wm) / etap).powi(2);
+            visible * dot(&wi, &wm).abs() / denom * (1.0 - reflectance)
+        }
+    }
+
+    fn flags(&self) -> BsdfFlags {
+        let lobe = if self.distribution.effectively_smooth() { BsdfFlags::SPECULAR } else { BsdfFlags::GLOSSY };
+        BsdfFlags::REFLECTION | BsdfFlags::TRANSMISSION | lobe
+    }
+}
+
+/// Bounces a light path may take between the interfaces of a `LayeredBxdf`.
+const LAYERED_MAX_DEPTH: usize = 10;
+
+/// A coat over another BxDF, with an absorbing layer between them. Light can bounce between the
+/// two interfaces any number of times, so everything is estimated with random walks through the
+/// layers, position-free (Guo et al. 2018, as in pbrt-v4): `sample` and `eval` are unbiased, `pdf`
+/// is a stochastic approximation. Two-sided: seen from below, the coat is still on top.
+pub struct LayeredBxdf {
+    top: Box<dyn Bxdf>,
+    bottom: Box<dyn Bxdf>,
+    /// Optical depth of the layer at normal incidence, per channel.
+    absorption: Color,
+}
+
+impl LayeredBxdf {
+    pub fn new(top: impl Bxdf + 'static, bottom: Box<dyn Bxdf>, absorption: Color) -> Self {
+        Self { top: Box::new(top), bottom, absorption }
+    }
+
+    /// Beer-Lambert transmittance of one crossing of the layer along `w`.
+    fn transmittance(&self, w: Vec3) -> Color {
+        let distance = 1.0 / w.z().abs();
+        Color::with_values(
+            f64::exp(-self.absorption.x() * distance),
+            f64::exp(-self.absorption.y() * distance),
+            f64::exp(-self.absorption.z() * distance),
+        )
+    }
+}
+
+/// A usable sample of `bxdf` that transmits if `transmission` and reflects otherwise. Rejecting
+/// the other kind keeps `f cos θ / pdf` an unbiased estimate of the kind asked for.
+fn sample_kind(bxdf: &dyn Bxdf, wo: Vec3, transmission: bool) -> Option<BsdfSample> {
+    let sample = bxdf.sample(wo, [random_float(), random_float(), random_float()])?;
+    let usable = sample.pdf > 0.0 && sample.wi.z() != 0.0 && !is_black(sample.f);
+    (usable && sample.flags.is_transmission() == transmission).then_some(sample)
+}
+
+fn is_black(c: Color) -> bool {
+    c.x() == 0.0 && c.y() == 0.0 && c.z() == 0.0
+}
+
+fn max_component(c: Color) -> f64 {
+    c.x().max(c.y()).max(c.z())
+}
+
+impl Bxdf for LayeredBxdf {
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Color {
+        let (wo, wi) = if wo.z() < 0.0 { (-wo, -wi) } else { (wo, wi) };
+        // The path enters through the top and leaves through the top when it reflects, through the
+        // bottom when it transmits.
+        let reflection = same_hemisphere(wo, wi);
+        let (exit, non_exit): (&dyn Bxdf, &dyn Bxdf) =
+            if reflection { (&*self.top, &*self.bottom) } else { (&*self.bottom, &*self.top) };
+
+        let mut f = if reflection { self.top.eval(wo, wi) } else { Color::new() };
+
+        // Directions into the layers from both ends; `wis` stands in for a light at `wi`.
+        let Some(wos) = sample_kind(&*self.top, wo, true) else { return f };
+        let Some(wis) = sample_kind(exit, wi, true) else { return f };
+
+        // Entering compresses radiance by 1/η², which our transmission leaves out because it cancels
+        // on the way out. Here only `wos` enters while `wis` leaves in the adjoint sense, so it doesn't.
+        let mut beta = wos.f * wos.wi.z().abs() / (wos.pdf * wos.eta * wos.eta);
+        let mut at_top = true;
+        let mut w = wos.wi;
+        for depth in 0..LAYERED_MAX_DEPTH {
+            if depth > 3 && max_component(beta) < 0.25 {
+                let q = f64::max(0.0, 1.0 - max_component(beta));
+                if random_float() < q {
+                    break;
+                }
+                beta /= 1.0 - q;
+            }
+
+            at_top = !at_top;
+            beta = beta * self.transmittance(w);
+
+            if at_top == reflection {
+                // At the exit interface, which `wis` and the next-event terms below already cover
+                // leaving through: keep bouncing.
+                let Some(bs) = sample_kind(exit, -w, false) else { break };
+                beta = beta * bs.f * bs.wi.z().abs() / bs.pdf;
+                w = bs.wi;
+                continue;
+            }
+
+            // At the other interface: connect to the exit direction through `wis`...
+            if !non_exit.flags().is_specular() {
+                let weight = if exit.flags().is_specular() {
+                    1.0
+                } else {
+                    power_heuristic(wis.pdf, non_exit.pdf(-w, -wis.wi))
+                };
+                f += beta * non_exit.eval(-w, -wis.wi) * wis.wi.z().abs() * weight * self.transmittance(wis.wi) * wis.f
+                    / wis.pdf;
+            }
+
+            // ...and through a sample of this interface, which also continues the walk.
+            let Some(bs) = sample_kind(non_exit, -w, false) else { break };
+            beta = beta * bs.f * bs.wi.z().abs() / bs.pdf;
+            w = bs.wi;
+
+            if !exit.flags().is_specular() {
+                let f_exit = exit.eval(-w, wi);
+                if !is_black(f_exit) {
+                    let weight = if non_exit.flags().is_specular() {
+                        1.0
+                    } else {
+                        power_heuristic(bs.pdf, exit.pdf(-w, wi))
+                    };
+                    f += beta * self.transmittance(bs.wi) * f_exit * weight;
+                }
+            }
+        }
+        f
+    }
+
+    fn sample(&self, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        let flip = wo.z() < 0.0;
+        let wo = if flip { -wo } else { wo };
+
+        let first = self.top.sample(wo, u)?;
+        if first.pdf == 0.0 || first.wi.z() == 0.0 {
+            return None;
+        }
+        if !first.flags.is_transmission() {
+            return Some(BsdfSample { wi: if flip { -first.wi } else { first.wi }, ..first });
+        }
+
+        let mut f = first.f * first.wi.z().abs();
+        let mut pdf = first.pdf;
+        let mut specular_path = first.flags.is_specular();
+        let mut diffuse_path = false;
+        let mut at_top = true;
+        let mut w = first.wi;
+        for depth in 0..LAYERED_MAX_DEPTH {
+            let rr_beta = max_component(f) / pdf;
+            if depth > 3 && rr_beta < 0.25 {
+                let q = f64::max(0.0, 1.0 - rr_beta);
+                if random_float() < q {
+                    return None;
+                }
+                pdf *= 1.0 - q;
+            }
+
+            at_top = !at_top;
+            f = f * self.transmittance(w);
+
+            let interface = if at_top { &self.top } else { &self.bottom };
+            let bs = interface.sample(-w, [random_float(), random_float(), random_float()])?;
+            if bs.pdf == 0.0 || bs.wi.z() == 0.0 {
+                return None;
+            }
+            f = f * bs.f;
+            pdf *= bs.pdf;
+            specular_path &= bs.flags.is_specular();
+            diffuse_path |= bs.flags.contains(BsdfFlags::DIFFUSE);
+            w = bs.wi;
+
+            if bs.flags.is_transmission() {
+                let kind = if same_hemisphere(wo, w) { BsdfFlags::REFLECTION } else { BsdfFlags::TRANSMISSION };
+                let lobe = if specular_path {
+                    BsdfFlags::SPECULAR
+                } else if diffuse_path {
+                    BsdfFlags::DIFFUSE
+                } else {
+                    BsdfFlags::GLOSSY
+                };
+                return Some(BsdfSample { f, wi: if flip { -w } else { w }, pdf, flags: kind | lobe, eta: 1.0 });
+            }
+            f *= w.z().abs();
+        }
+        None
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> f64 {
+        let (wo, wi) = if wo.z() < 0.0 { (-wo, -wi) } else { (wo, wi) };
+        let (top, bottom) = (&*self.top, &*self.bottom);
+
+        let mut pdf = 0.0;
+        if same_hemisphere(wo, wi) {
+            // Reflection off the top, plus transmission, reflection off the bottom and transmission.
+            pdf += top.pdf(wo, wi);
+            if let (Some(wos), Some(wis)) = (sample_kind(top, wo, true), sample_kind(top, wi, true)) {
+                if top.flags().is_specular() {
+                    pdf += bottom.pdf(-wos.wi, -wis.wi);
+                } else if let Some(rs) = sample_kind(bottom, -wos.wi, false) {
+                    if bottom.flags().is_specular() {
+                        pdf += top.pdf(-rs.wi, wi);
+                    } else {
+                        let r_pdf = bottom.pdf(-wos.wi, -wis.wi);
+                        pdf += power_heuristic(wis.pdf, r_pdf) * r_pdf;
+                        let t_pdf = top.pdf(-rs.wi, wi);
+                        pdf += power_heuristic(rs.pdf, t_pdf) * t_pdf;
+                    }
+                }
+            }
+        } else if let (Some(wos), Some(wis)) = (sample_kind(top, wo, true), sample_kind(bottom, wi, true)) {
+            // Transmission through both interfaces.
+            pdf += if top.flags().is_specular() {
+                bottom.pdf(-wos.wi, wi)
+            } else if bottom.flags().is_specular() {
+                top.pdf(wo, -wis.wi)
+            } else {
+                (top.pdf(wo, -wis.wi) + bottom.pdf(-wos.wi, wi)) / 2.0
+            };
+        }
+
+        // Mixed with a uniform density, so the estimate is never zero where the BSDF isn't.
+        0.1 / (4.0 * PI) + 0.9 * pdf
+    }
+
+    fn flags(&self) -> BsdfFlags {
+        let (top, bottom) = (self.top.flags(), self.bottom.flags());
+        let mut flags = BsdfFlags::REFLECTION;
+        if top.is_specular() {
+            flags = flags | BsdfFlags::SPECULAR;
+        }
+        if top.contains(BsdfFlags::DIFFUSE) || bottom.contains(BsdfFlags::DIFFUSE) {
+            flags = flags | BsdfFlags::DIFFUSE;
+        } else if top.contains(BsdfFlags::GLOSSY) || bottom.contains(BsdfFlags::GLOSSY) {
+            flags = flags | BsdfFlags::GLOSSY;
+        }
+        if top.is_transmission() && bottom.is_transmission() {
+            flags = flags | BsdfFlags::TRANSMISSION;
+        }
+        flags
+    }
+}
+
+/// Refracts `wo` through a surface with normal `n` and relative index `eta` (inside over the side `n`
+/// points to). Returns the transmitted direction and the relative index η_t / η_i actually crossed,
+/// or `None` on total internal reflection.
+pub fn refract(wo: Vec3, n: Vec3, eta: f64) -> Option<(Vec3, f64)> {
+    let (mut n, mut eta) = (n, eta);
+    let mut cos_theta_i = dot(&n, &wo);
+    if cos_theta_i < 0.0 {
+        eta = 1.0 / eta;
+        cos_theta_i = -cos_theta_i;
+        n = -n;
+    }
+
+    let sin2_theta_t = f64::max(0.0, 1.0 - cos_theta_i * cos_theta_i) / (eta * eta);
+    if sin2_theta_t >= 1.0 {
+        return None;
+    }
+    let cos_theta_t = f64::sqrt(1.0 - sin2_theta_t);
+    Some((-wo / eta + (cos_theta_i / eta - cos_theta_t) * n, eta))
+}
@@ -3,9 +3,16 @@ use indicatif::ProgressBar;
 use rayon::iter::{ParallelBridge, ParallelIterator};
 
 use crate::{
-    color::{write_color, Color}, hittable::Hittable, interval::Interval, ray::{Point3, Ray}, utils::{degrees_to_radians, random_float}, vec3::{self, random_in_unit_disk, unit_vector, Vec3}
+    bsdf::{Bsdf, BsdfFlags}, color::{write_color, Color}, hittable::{HitRecord, Hittable}, hittable_list::HittableList, interval::Interval, material::PathContext, medium::{MediumStack, RandomWalk}, sampling::{power_heuristic, sample_uniform_sphere}, spectrum::{PathSpectrum, RgbPath, SpectralPath}, ray::{Point3, Ray, RayDifferential}, utils::{degrees_to_radians, random_float}, vec3::{self, random_in_unit_disk, unit_vector, Vec3}
 };
 
+/// Scattering events a path may have inside media, on top of its surface bounces. Dense media
+/// with a high albedo take hundreds.
+const MAX_SCATTERINGS: u32 = 256;
+
+/// Fraction of the distance to a light that shadow rays stop short of, so they don't hit the light.
+const SHADOW_EPSILON: f64 = 1e-4;
+
 pub struct Camera {
     pub aspect_ratio: f32,
     pub image_width: u32,
@@ -17,7 +24,13 @@ pub struct Camera {
     pub vup: Vec3,
     pub defocus_angle: f64,
     pub focus_distance: f64,
+    /// Radiance for rays that escape the scene; `None` keeps the blue-white sky gradient.
+    pub background: Option<Color>,
+    /// Trace a few wavelengths per path instead of RGB. Slower, but gets dispersion and the colors
+    /// of interreflections right.
+    pub spectral: bool,
     pixel_samples_scale: f64,
+    differential_scale: f64,
     image_height: u32,
     center: Point3,
     pixel00_loc: Point3,
@@ -43,7 +56,10 @@ impl Default for Camera {
             vup: Vec3::with_values(0.0, 1.0, 0.0),
             defocus_angle: 0.0,
             focus_distance: 10.0,
+            background: None,
+            spectral: false,
             pixel_samples_scale: 0.1,
+            differential_scale: 1.0,
             image_height: 100,
             center: Point3::with_values(0., 0., 0.),
             pixel00_loc: Point3::new(),
@@ -59,7 +75,11 @@ impl Default for Camera {
 }
 
 impl Camera {
-    pub fn render(&mut self, world: &dyn Hittable) {
+    /// Renders `world` to `output.jpg`. `lights` holds the emitters to send shadow rays toward, put
+    /// there together with `world` by `HittableList::add_light`. Emitters missing from it are still
+    /// found by bouncing into them, just with more noise; scenes lit only by the background pass an
+    /// empty list.
+    pub fn render(&mut self, world: &dyn Hittable, lights: &HittableList) {
         self.initialize();
 
         let mut img: ImageBuffer<Rgb<u8>, Vec<u8>> =
@@ -72,7 +92,11 @@ impl Camera {
                 let mut pixel_color = Color::new();
                 for _ in 0..self.samples_per_pixel {
                     let ray = self.get_ray(x, y);
-                    pixel_color += self.ray_color(&ray, self.max_depth, world);
+                    pixel_color += if self.spectral {
+                        self.ray_color(&ray, world, lights, SpectralPath::sample(random_float()))
+                    } else {
+                        self.ray_color(&ray, world, lights, RgbPath::sample(random_float()))
+                    };
                     bar.inc(1);
                 }
                 write_color(pixel, self.pixel_samples_scale * pixel_color);
@@ -94,6 +118,9 @@ impl Camera {
         };
 
         self.pixel_samples_scale = 1.0 / self.samples_per_pixel as f64;
+        // With many samples per pixel each one only has to cover its share of the pixel, so the
+        // footprint can shrink and textures stay sharp (pbrt, section 6.2.1).
+        self.differential_scale = f64::max(0.125, 1.0 / f64::sqrt(self.samples_per_pixel as f64));
 
         self.center = self.look_from;
 
@@ -133,7 +160,15 @@ impl Camera {
         let ray_origin = if self.defocus_angle <= 0.0 { self.center } else { self.defocus_disk_sample() };
         let ray_direction = pixel_sample - ray_origin;
 
-        Ray::with_values(ray_origin, ray_direction)
+        // The neighbouring pixels, seen through the same point on the lens.
+        let differential = RayDifferential {
+            rx_origin: ray_origin,
+            rx_direction: unit_vector(&(ray_direction + self.differential_scale * self.pixel_delta_u)),
+            ry_origin: ray_origin,
+            ry_direction: unit_vector(&(ray_direction + self.differential_scale * self.pixel_delta_v)),
+        };
+
+        Ray::with_differential(ray_origin, ray_direction, Some(differential))
     }
 
     fn sample_square(&self) -> Vec3 {
@@ -145,19 +180,149 @@ impl Camera {
         self.center + (p[0] * self.defocus_disk_u) + (p[1] * self.defocus_disk_v)
     }
 
-    fn ray_color(&self, ray: &Ray, depth: u32, world: &dyn Hittable) -> Color {
-        if depth == 0 {
-            return Color::new();
-        }
+    /// Follows one path through the scene, sampling the BSDF at each hit and accumulating emission
+    /// weighted by the path throughput. The path keeps track of the dielectric media it is inside, to
+    /// attenuate it along the way and to find the relative index of refraction at each interface. It
+    /// also carries one sampled wavelength; the first time a dispersive interface depends on it, the
+    /// path commits to that wavelength and is tinted with its color from then on.
+    ///
+    /// At non-specular vertices outside media, a shadow ray toward a sampled light estimates direct
+    /// light too. Its contribution and the emission the next bounce finds are combined with the power
+    /// heuristic, so each light is reached by whichever strategy samples it better.
+    fn ray_color<S: PathSpectrum>(&self, ray: &Ray, world: &dyn Hittable, lights: &HittableList, mut spectrum: S) -> Color {
+        let mut radiance = S::Value::default();
+        let mut throughput = spectrum.upsample(Color::with_values(1.0, 1.0, 1.0));
+        let mut ray = ray.clone();
+        let mut media = MediumStack::new();
+        let mut bounces = 0;
+        let mut walk = RandomWalk::new(random_float());
+        let mut scatterings = 0;
+        let mut dispersed = false;
+        // Origin and BSDF density of the last bounce, when a light sample competed with it.
+        let mut light_sampled_from: Option<(Point3, f64)> = None;
+
+        while bounces < self.max_depth {
+            let Some(mut hit_record) = world.hit(&ray, Interval::POSITIVE) else {
+                return spectrum.to_rgb(radiance + throughput * spectrum.upsample(self.background_color(&ray)));
+            };
+
+            if let Some(medium) = media.current() {
+                let (scattered, weight) = walk.step(&medium, hit_record.t * ray.direction().length(), random_float());
+                throughput = throughput * spectrum.upsample(weight);
+                if let Some(distance) = scattered {
+                    // A step of the random walk through the medium, which doesn't count as a bounce.
+                    scatterings += 1;
+                    if scatterings > MAX_SCATTERINGS {
+                        break;
+                    }
+                    let origin = ray.at(distance / ray.direction().length());
+                    ray = Ray::new(origin, sample_uniform_sphere((random_float(), random_float())));
+                    light_sampled_from = None;
+                    continue;
+                }
+            }
+
+            let interior = hit_record.mat.interior();
+            if let Some(interior) = interior {
+                if !media.is_true_interface(&interior, hit_record.front_face) {
+                    // A higher priority medium fills this space, so the surface isn't really there.
+                    media.cross(interior, hit_record.front_face);
+                    ray = hit_record.spawn_ray_with_differential(ray.direction(), ray.differential());
+                    continue;
+                }
+            }
+
+            hit_record.compute_footprint(&ray);
+            let emitted = hit_record.mat.emitted(hit_record.u, hit_record.v, &hit_record.p);
+            if emitted.length_squared() > 0.0 {
+                // Only lights could also have been reached by `sample_light`; other emitters are
+                // found by bouncing alone and keep all of their contribution.
+                let weight = match light_sampled_from {
+                    Some((origin, bsdf_pdf)) if hit_record.light => {
+                        power_heuristic(bsdf_pdf, lights.pdf_value(origin, ray.direction()))
+                    }
+                    _ => 1.0,
+                };
+                radiance += throughput * spectrum.upsample(weight * emitted);
+            }
+
+            let exterior = interior.and_then(|interior| media.exterior(&interior, hit_record.front_face));
+            let dispersive = [interior, exterior].iter().flatten().any(|medium| medium.index_of_refraction.is_dispersive());
+            if dispersive && !dispersed {
+                throughput = throughput * spectrum.single_wavelength();
+                dispersed = true;
+            }
 
-        if let Some(hit_record) = world.hit(ray, Interval::UNIVERSE) {
-            if let Some(scatter_record) = hit_record.mat.as_ref().scatter(ray, &hit_record) {
-                return scatter_record.attenuation * self.ray_color(&scatter_record.scattered, depth - 1, world);
+            let context = PathContext {
+                exterior_ior: exterior.map_or(1.0, |exterior| exterior.index_of_refraction.at(spectrum.wavelength())),
+                wavelength: spectrum.wavelength(),
+            };
+            let Some(bsdf) = hit_record.mat.bsdf(&hit_record, &context) else {
+                break;
+            };
+            let wo = -unit_vector(&ray.direction());
+
+            let sample_lights = !lights.objects.is_empty() && media.current().is_none() && bsdf.flags().is_non_specular();
+            if sample_lights {
+                radiance += throughput * spectrum.upsample(Self::sample_light(&hit_record, &bsdf, wo, world, lights));
             }
 
+            let Some(sample) = bsdf.sample(wo, [random_float(), random_float(), random_float()]) else {
+                break;
+            };
+
+            let cos_theta = vec3::dot(&sample.wi, &hit_record.outward_shading_normal()).abs();
+            throughput = throughput * spectrum.upsample(sample.f * (cos_theta / sample.pdf));
+            // Delta lobes can't be reached by light samples, so what they find counts in full. For the
+            // rest, `bsdf.pdf` rather than `sample.pdf`, which layered BSDFs only know up to a factor,
+            // keeps both weights computed from the same density.
+            let bsdf_pdf = (sample_lights && !sample.flags.is_specular()).then(|| bsdf.pdf(wo, sample.wi));
+
+            if let Some(interior) = interior {
+                if sample.flags.is_transmission() {
+                    media.cross(interior, hit_record.front_face);
+                }
+            }
+
+            // Differentials only make sense along sharp lobes; after a diffuse bounce the footprint
+            // would cover most of the scene anyway.
+            ray = if sample.flags.contains(BsdfFlags::DIFFUSE) {
+                hit_record.spawn_ray(sample.wi)
+            } else if sample.flags.is_transmission() {
+                hit_record.spawn_ray_with_differential(sample.wi, hit_record.refracted_differential(&ray, 1.0 / sample.eta))
+            } else {
+                hit_record.spawn_ray_with_differential(sample.wi, hit_record.reflected_differential(&ray))
+            };
+            light_sampled_from = bsdf_pdf.map(|pdf| (ray.origin(), pdf));
+            bounces += 1;
+        }
+
+        spectrum.to_rgb(radiance)
+    }
+
+    /// Light arriving directly from a random point on a random light, scattered toward `wo` and
+    /// weighted against the chance of the BSDF sampling the same direction.
+    fn sample_light(hit_record: &HitRecord, bsdf: &Bsdf, wo: Vec3, world: &dyn Hittable, lights: &HittableList) -> Color {
+        let direction = lights.sample(hit_record.p);
+        let ray = hit_record.spawn_ray(direction);
+        let Some(light) = lights.hit(&ray, Interval::POSITIVE) else {
+            return Color::new();
+        };
+
+        let pdf = lights.pdf_value(ray.origin(), direction);
+        if pdf == 0.0 || world.occluded(&ray, Interval::new(0.0, light.t * (1.0 - SHADOW_EPSILON))) {
             return Color::new();
-            // let direction = hit_record.normal + random_unit_vector();
-            // return 0.5 * self.ray_color(&Ray::with_values(hit_record.p, direction), depth - 1, world);
+        }
+
+        let wi = unit_vector(&direction);
+        let cos_theta = vec3::dot(&wi, &hit_record.outward_shading_normal()).abs();
+        let weight = power_heuristic(pdf, bsdf.pdf(wo, wi));
+        bsdf.eval(wo, wi) * light.mat.emitted(light.u, light.v, &light.p) * (weight * cos_theta / pdf)
+    }
+
+    fn background_color(&self, ray: &Ray) -> Color {
+        if let Some(background) = self.background {
+            return background;
         }
 
         let unit_direction = unit_vector(&ray.direction());
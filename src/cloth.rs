@@ -0,0 +1,124 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use crate::{
+    bsdf::{same_hemisphere, Bsdf, BsdfFlags, BsdfSample, Bxdf, DiffuseBxdf, MixtureBxdf},
+    color::Color,
+    hittable::HitRecord,
+    material::{Material, PathContext},
+    sampling::sample_cosine_hemisphere,
+    texture::{SolidColor, Texture, TextureContext},
+    vec3::Vec3,
+};
+
+/// Cloth and velvet: a diffuse base under the sheen of fibers standing up from the surface, which
+/// light grazing across them catches. `sheen` colors the fibers and `roughness` in [0, 1] spreads
+/// them from standing straight up (a tight rim, like velvet) to pointing every which way (soft cotton).
+pub struct Cloth {
+    color: Arc<dyn Texture>,
+    sheen: Arc<dyn Texture>,
+    roughness: Arc<dyn Texture>,
+}
+
+impl Cloth {
+    pub fn new(color: Color, sheen: Color, roughness: f64) -> Self {
+        Self::from_textures(Arc::new(SolidColor::new(color)), Arc::new(SolidColor::new(sheen)), Arc::new(SolidColor::gray(roughness)))
+    }
+
+    /// Cloth with spatially varying colors, and roughness read through `Texture::scalar`.
+    pub fn from_textures(color: Arc<dyn Texture>, sheen: Arc<dyn Texture>, roughness: Arc<dyn Texture>) -> Self {
+        Self { color, sheen, roughness }
+    }
+}
+
+impl Material for Cloth {
+    fn bsdf(&self, hit_record: &HitRecord, _context: &PathContext) -> Option<Bsdf> {
+        let ctx = TextureContext::from_hit(hit_record);
+        let white = Color::with_values(1.0, 1.0, 1.0);
+
+        // Both lobes sample the cosine-weighted hemisphere, so an even split is as good as any.
+        let mut mixture = MixtureBxdf::default();
+        mixture.add(white, 0.5, DiffuseBxdf::new(self.color.value(&ctx)));
+        mixture.add(white, 0.5, SheenBxdf::new(self.sheen.value(&ctx), self.roughness.scalar(&ctx)));
+        Some(Bsdf::new(hit_record, mixture))
+    }
+}
+
+/// The "Charlie" microfiber sheen of Estevez and Kulla (2017): a distribution of fibers whose
+/// normals crowd toward the tangent plane, with their fitted shadowing term.
+struct SheenBxdf {
+    color: Color,
+    roughness: f64,
+}
+
+impl SheenBxdf {
+    fn new(color: Color, roughness: f64) -> Self {
+        // The fit of the shadowing term is only valid down to a roughness of about 0.07.
+        Self { color, roughness: roughness.clamp(0.07, 1.0) }
+    }
+
+    fn d(&self, cos_theta_h: f64) -> f64 {
+        let inv_r = 1.0 / self.roughness;
+        let sin_theta_h = f64::sqrt(f64::max(0.0, 1.0 - cos_theta_h * cos_theta_h));
+        (2.0 + inv_r) * sin_theta_h.powf(inv_r) / (2.0 * PI)
+    }
+
+    /// Their fit of the shadowing exponent, interpolated between roughness 0 and 1 in (1 - r)².
+    fn l(&self, x: f64) -> f64 {
+        let t = (1.0 - self.roughness) * (1.0 - self.roughness);
+        let lerp = |r0: f64, r1: f64| t * r0 + (1.0 - t) * r1;
+        let (a, b, c) = (lerp(25.3245, 21.5473), lerp(3.32435, 3.82987), lerp(0.16801, 0.19823));
+        let (d, e) = (lerp(-1.27393, -1.97760), lerp(-4.85967, -4.32054));
+        a / (1.0 + b * x.powf(c)) + d * x + e
+    }
+
+    fn lambda(&self, cos_theta: f64) -> f64 {
+        if cos_theta < 0.5 {
+            self.l(cos_theta).exp()
+        } else {
+            (2.0 * self.l(0.5) - self.l(1.0 - cos_theta)).exp()
+        }
+    }
+}
+
+impl Bxdf for SheenBxdf {
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Color {
+        if !same_hemisphere(wo, wi) {
+            return Color::new();
+        }
+        let (cos_theta_o, cos_theta_i) = (wo.z().abs(), wi.z().abs());
+        let wh = wo + wi;
+        if wh.length_squared() == 0.0 || cos_theta_o == 0.0 || cos_theta_i == 0.0 {
+            return Color::new();
+        }
+
+        let cos_theta_h = wh.z().abs() / wh.length();
+        let g = 1.0 / (1.0 + self.lambda(cos_theta_o) + self.lambda(cos_theta_i));
+        self.d(cos_theta_h) * g / (4.0 * cos_theta_o * cos_theta_i) * self.color
+    }
+
+    fn sample(&self, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        let mut wi = sample_cosine_hemisphere((u[1], u[2]));
+        if wo.z() < 0.0 {
+            wi = Vec3::with_values(wi.x(), wi.y(), -wi.z());
+        }
+
+        Some(BsdfSample {
+            f: self.eval(wo, wi),
+            wi,
+            pdf: self.pdf(wo, wi),
+            flags: BsdfFlags::REFLECTION | BsdfFlags::GLOSSY,
+            eta: 1.0,
+        })
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> f64 {
+        if !same_hemisphere(wo, wi) {
+            return 0.0;
+        }
+        wi.z().abs() / PI
+    }
+
+    fn flags(&self) -> BsdfFlags {
+        BsdfFlags::REFLECTION | BsdfFlags::GLOSSY
+    }
+}
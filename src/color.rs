@@ -11,6 +11,24 @@ pub fn linear_to_gamma(linear_component: f64) -> f64 {
     0.0
 }
 
+/// Inverse of the sRGB transfer curve, for 8-bit image data read from disk.
+pub fn srgb_to_linear(encoded: f64) -> f64 {
+    if encoded <= 0.04045 {
+        encoded / 12.92
+    } else {
+        f64::powf((encoded + 0.055) / 1.055, 2.4)
+    }
+}
+
+/// The sRGB transfer curve, inverse of `srgb_to_linear`.
+pub fn linear_to_srgb(linear: f64) -> f64 {
+    if linear <= 0.0031308 {
+        linear * 12.92
+    } else {
+        1.055 * f64::powf(linear, 1.0 / 2.4) - 0.055
+    }
+}
+
 pub fn write_color(pixel: &mut Rgb<u8>, pixel_color: Color) {
     let mut r = pixel_color.x();
     let mut g = pixel_color.y();
@@ -0,0 +1,318 @@
+use std::{f64::consts::PI, ops::{Add, Div, Mul, Sub}, sync::LazyLock};
+
+use crate::{color::Color, spectrum::{cie_xyz, xyz_to_linear_srgb, WAVELENGTH_MAX, WAVELENGTH_MIN}, vec3::Vec3};
+
+/// Unpolarized Fresnel reflectance at a boundary between dielectrics, where `eta` is the index of
+/// refraction inside relative to outside and `cos_theta_i` is negative for light arriving from inside.
+pub fn fr_dielectric(cos_theta_i: f64, eta: f64) -> f64 {
+    let (cos_theta_i, eta) = if cos_theta_i < 0.0 {
+        (f64::min(-cos_theta_i, 1.0), 1.0 / eta)
+    } else {
+        (f64::min(cos_theta_i, 1.0), eta)
+    };
+
+    let sin2_theta_t = (1.0 - cos_theta_i * cos_theta_i) / (eta * eta);
+    if sin2_theta_t >= 1.0 {
+        // Total internal reflection.
+        return 1.0;
+    }
+    let cos_theta_t = f64::sqrt(1.0 - sin2_theta_t);
+
+    let r_parallel = (eta * cos_theta_i - cos_theta_t) / (eta * cos_theta_i + cos_theta_t);
+    let r_perpendicular = (cos_theta_i - eta * cos_theta_t) / (cos_theta_i + eta * cos_theta_t);
+    (r_parallel * r_parallel + r_perpendicular * r_perpendicular) / 2.0
+}
+
+/// Just enough complex arithmetic for the conductor and thin-film Fresnel equations.
+#[derive(Clone, Copy)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    /// Squared magnitude.
+    fn norm(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    /// Principal square root.
+    fn sqrt(self) -> Self {
+        let n = self.norm().sqrt();
+        if n == 0.0 {
+            return Self::new(0.0, 0.0);
+        }
+
+        let t1 = f64::sqrt(0.5 * (n + self.re.abs()));
+        let t2 = 0.5 * self.im / t1;
+        if self.re >= 0.0 {
+            Self::new(t1, t2)
+        } else {
+            Self::new(t2.abs(), t1.copysign(self.im))
+        }
+    }
+
+    fn exp(self) -> Self {
+        let magnitude = self.re.exp();
+        Self::new(magnitude * self.im.cos(), magnitude * self.im.sin())
+    }
+}
+
+impl From<f64> for Complex {
+    fn from(re: f64) -> Self {
+        Self::new(re, 0.0)
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(self.re * rhs.re - self.im * rhs.im, self.re * rhs.im + self.im * rhs.re)
+    }
+}
+
+impl Div for Complex {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        let scale = 1.0 / rhs.norm();
+        Self::new(
+            scale * (self.re * rhs.re + self.im * rhs.im),
+            scale * (self.im * rhs.re - self.re * rhs.im),
+        )
+    }
+}
+
+/// Unpolarized Fresnel reflectance of a conductor with complex index of refraction `eta + i k`
+/// (relative to the outside medium), for light arriving at `cos_theta_i` to the normal.
+fn fr_complex(cos_theta_i: f64, eta: Complex) -> f64 {
+    let cos_theta_i = cos_theta_i.clamp(0.0, 1.0);
+    let sin2_theta_i = 1.0 - cos_theta_i * cos_theta_i;
+    let sin2_theta_t = Complex::from(sin2_theta_i) / (eta * eta);
+    let cos_theta_t = (Complex::from(1.0) - sin2_theta_t).sqrt();
+
+    let cos_i = Complex::from(cos_theta_i);
+    let r_parallel = (eta * cos_i - cos_theta_t) / (eta * cos_i + cos_theta_t);
+    let r_perpendicular = (cos_i - eta * cos_theta_t) / (cos_i + eta * cos_theta_t);
+    (r_parallel.norm() + r_perpendicular.norm()) / 2.0
+}
+
+/// Complex index of refraction `eta + i k` of a conductor, sampled at the red, green and blue
+/// primaries (roughly 650, 550 and 450 nm).
+#[derive(Clone, Copy)]
+pub struct ComplexIor {
+    eta: Color,
+    k: Color,
+}
+
+impl ComplexIor {
+    pub fn new(eta: Color, k: Color) -> Self {
+        Self { eta, k }
+    }
+
+    /// A conductor whose reflectance at normal incidence is `reflectance`, with the edge tint set
+    /// to the same color (Gulbrandsen 2014). Lets albedo-driven metals go under a thin film.
+    pub fn from_reflectance(reflectance: Color) -> Self {
+        let channel = |r: f64| {
+            let r = r.clamp(0.0, 0.999);
+            let sqrt_r = r.sqrt();
+            let eta = r * (1.0 - r) / (1.0 + r) + (1.0 - r) * (1.0 + sqrt_r) / (1.0 - sqrt_r);
+            let k2 = (r * (eta + 1.0) * (eta + 1.0) - (eta - 1.0) * (eta - 1.0)) / (1.0 - r);
+            (eta, k2.max(0.0).sqrt())
+        };
+        let (red, green, blue) = (channel(reflectance.x()), channel(reflectance.y()), channel(reflectance.z()));
+        Self::new(Color::with_values(red.0, green.0, blue.0), Color::with_values(red.1, green.1, blue.1))
+    }
+
+    pub fn gold() -> Self {
+        Self::new(Color::with_values(0.143, 0.374, 1.442), Color::with_values(3.983, 2.386, 1.603))
+    }
+
+    pub fn copper() -> Self {
+        Self::new(Color::with_values(0.200, 0.924, 1.102), Color::with_values(3.912, 2.452, 2.142))
+    }
+
+    pub fn aluminum() -> Self {
+        Self::new(Color::with_values(1.657, 0.880, 0.521), Color::with_values(9.224, 6.270, 4.837))
+    }
+
+    pub fn silver() -> Self {
+        Self::new(Color::with_values(0.155, 0.117, 0.138), Color::with_values(4.828, 3.122, 2.147))
+    }
+
+    pub fn platinum() -> Self {
+        Self::new(Color::with_values(2.375, 2.085, 1.845), Color::with_values(4.265, 3.715, 3.137))
+    }
+
+    pub fn iron() -> Self {
+        Self::new(Color::with_values(2.870, 2.950, 2.650), Color::with_values(3.080, 2.930, 2.810))
+    }
+
+    pub fn chromium() -> Self {
+        Self::new(Color::with_values(3.110, 3.180, 2.390), Color::with_values(3.310, 3.330, 3.220))
+    }
+
+    /// Per-channel Fresnel reflectance at `cos_theta_i`.
+    pub fn reflectance(&self, cos_theta_i: f64) -> Color {
+        Color::with_values(
+            fr_complex(cos_theta_i, Complex::new(self.eta.x(), self.k.x())),
+            fr_complex(cos_theta_i, Complex::new(self.eta.y(), self.k.y())),
+            fr_complex(cos_theta_i, Complex::new(self.eta.z(), self.k.z())),
+        )
+    }
+
+    /// The index at `lambda` nanometers, linearly interpolated between the primaries and held
+    /// constant beyond them.
+    fn at(&self, lambda: f64) -> Complex {
+        let (eta, k) = if lambda < 550.0 {
+            let t = ((lambda - 450.0) / 100.0).clamp(0.0, 1.0);
+            (self.eta.z() + t * (self.eta.y() - self.eta.z()), self.k.z() + t * (self.k.y() - self.k.z()))
+        } else {
+            let t = ((lambda - 550.0) / 100.0).clamp(0.0, 1.0);
+            (self.eta.y() + t * (self.eta.x() - self.eta.y()), self.k.y() + t * (self.k.x() - self.k.y()))
+        };
+        Complex::new(eta, k)
+    }
+}
+
+/// Wavelengths thin-film reflectance is integrated over to get its color.
+const FILM_SAMPLES: usize = 32;
+
+/// The film quadrature: each wavelength with its color matching functions, and the linear sRGB of
+/// a perfect reflector under it, which keeps a vanishing film from tinting anything.
+static FILM_QUADRATURE: LazyLock<(Vec<(f64, Vec3)>, Color)> = LazyLock::new(|| {
+    let step = (WAVELENGTH_MAX - WAVELENGTH_MIN) / FILM_SAMPLES as f64;
+    let samples: Vec<_> = (0..FILM_SAMPLES)
+        .map(|i| {
+            let lambda = WAVELENGTH_MIN + (i as f64 + 0.5) * step;
+            (lambda, cie_xyz(lambda))
+        })
+        .collect();
+    let white = xyz_to_linear_srgb(samples.iter().fold(Vec3::new(), |sum, &(_, xyz)| sum + xyz));
+    (samples, white)
+});
+
+/// A thin transparent layer on top of a surface, such as soap, oil or an anodized oxide, whose
+/// reflections interfere with the surface's own. `thickness` is in nanometers and
+/// `index_of_refraction` is relative to the medium outside the film.
+#[derive(Clone, Copy)]
+pub struct FilmLayer {
+    pub thickness: f64,
+    pub index_of_refraction: f64,
+}
+
+impl FilmLayer {
+    /// Per-channel reflectance of the film over a dielectric whose index is `eta` relative to the
+    /// outside, with `cos_theta_i` negative for light arriving from inside, as for `fr_dielectric`.
+    /// The film sits on the outer side of the interface either way. Neither layer absorbs, so the
+    /// rest is transmitted.
+    pub fn reflectance_over_dielectric(&self, cos_theta_i: f64, eta: f64) -> Color {
+        if cos_theta_i < 0.0 {
+            // Seen from inside, the stack is the dielectric, the film and then the outside.
+            self.reflectance(-cos_theta_i, eta, |_| Complex::from(1.0))
+        } else {
+            self.reflectance(cos_theta_i, 1.0, |_| Complex::from(eta))
+        }
+    }
+
+    /// Per-channel reflectance of the film over a conductor, for light arriving from outside.
+    pub fn reflectance_over_conductor(&self, cos_theta_i: f64, ior: &ComplexIor) -> Color {
+        self.reflectance(cos_theta_i.max(0.0), 1.0, |lambda| ior.at(lambda))
+    }
+
+    /// Airy's sum of the reflections between the incident medium of index `incident_ior` and the
+    /// substrate of index `substrate(λ)`, integrated against the color matching functions.
+    fn reflectance(&self, cos_theta_i: f64, incident_ior: f64, substrate: impl Fn(f64) -> Complex) -> Color {
+        let (samples, white) = &*FILM_QUADRATURE;
+        let mut xyz = Vec3::new();
+        for &(lambda, cmf) in samples {
+            xyz += self.airy(cos_theta_i.min(1.0), incident_ior, substrate(lambda), lambda) * cmf;
+        }
+        let rgb = xyz_to_linear_srgb(xyz);
+        Color::with_values(
+            (rgb.x() / white.x()).clamp(0.0, 1.0),
+            (rgb.y() / white.y()).clamp(0.0, 1.0),
+            (rgb.z() / white.z()).clamp(0.0, 1.0),
+        )
+    }
+
+    /// Unpolarized reflectance of the film at a single wavelength: the amplitudes reflected at
+    /// the top and bottom of the film form a geometric series whose ratio is the round-trip phase.
+    fn airy(&self, cos_theta_i: f64, n1: f64, n3: Complex, lambda: f64) -> f64 {
+        let n1 = Complex::from(n1);
+        let n2 = Complex::from(self.index_of_refraction);
+        let cos1 = Complex::from(cos_theta_i);
+        // Snell's law, carried on in complex form past total internal reflection and into the
+        // conductor, where the angles stop being real.
+        let sin2_1 = Complex::from(1.0 - cos_theta_i * cos_theta_i);
+        let cosine_in = |n: Complex| (Complex::from(1.0) - n1 * n1 * sin2_1 / (n * n)).sqrt();
+        let (cos2, cos3) = (cosine_in(n2), cosine_in(n3));
+
+        // e^{2iδ} with δ = 2π n₂ d cos θ₂ / λ, the phase a round trip through the film adds.
+        let delta = Complex::from(2.0 * PI * self.thickness / lambda) * n2 * cos2;
+        let round_trip = (Complex::new(0.0, 2.0) * delta).exp();
+
+        let airy = |r12: Complex, r23: Complex| {
+            ((r12 + r23 * round_trip) / (Complex::from(1.0) + r12 * r23 * round_trip)).norm()
+        };
+        let perpendicular = airy(
+            (n1 * cos1 - n2 * cos2) / (n1 * cos1 + n2 * cos2),
+            (n2 * cos2 - n3 * cos3) / (n2 * cos2 + n3 * cos3),
+        );
+        let parallel = airy(
+            (n2 * cos1 - n1 * cos2) / (n2 * cos1 + n1 * cos2),
+            (n3 * cos2 - n2 * cos3) / (n3 * cos2 + n2 * cos3),
+        );
+        (perpendicular + parallel) / 2.0
+    }
+}
+
+/// The Fresnel term of an opaque microfacet surface.
+#[derive(Clone, Copy)]
+pub enum Fresnel {
+    /// Exact, from a conductor's complex index of refraction.
+    Conductor(ComplexIor),
+    /// A conductor under a thin film, whose interference colors its reflections.
+    ThinFilmConductor(FilmLayer, ComplexIor),
+    /// Schlick's approximation from the reflectance at normal incidence.
+    Schlick(Color),
+}
+
+impl Fresnel {
+    pub fn reflectance(&self, cos_theta_i: f64) -> Color {
+        match self {
+            Self::Conductor(ior) => ior.reflectance(cos_theta_i),
+            Self::ThinFilmConductor(film, ior) => film.reflectance_over_conductor(cos_theta_i, ior),
+            Self::Schlick(r0) => {
+                let weight = schlick_weight(cos_theta_i);
+                (1.0 - weight) * *r0 + weight * Color::with_values(1.0, 1.0, 1.0)
+            }
+        }
+    }
+}
+
+/// (1 - cos θ)⁵, the grazing-angle falloff of Schlick's approximation.
+pub fn schlick_weight(cos_theta: f64) -> f64 {
+    (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5)
+}
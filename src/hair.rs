@@ -0,0 +1,339 @@
+use std::f64::consts::PI;
+
+use crate::{
+    bsdf::{Bsdf, BsdfFlags, BsdfSample, Bxdf},
+    color::Color,
+    fresnel::fr_dielectric,
+    hittable::HitRecord,
+    material::{Material, PathContext},
+    vec3::Vec3,
+};
+
+/// Scattering lobes modeled individually: R, TT and TRT. Everything after them is lumped into one.
+const P_MAX: usize = 3;
+
+/// Index of refraction of keratin.
+const ETA: f64 = 1.55;
+
+/// Angle the cuticle scales tilt the fiber surface by, in degrees.
+const SCALE_ANGLE: f64 = 2.0;
+
+/// Human hair and fur, after Chiang et al. (2016): light reflects off the fiber, passes through it,
+/// or bounces around inside, and the pigment absorbs along the way. The fiber runs along dpdu, as it
+/// does on curve primitives; the offset across it where light lands comes from how the normal faces
+/// the outgoing direction, so any round tube works, and a sphere reads as a ball of wound hair.
+/// `beta_m` and `beta_n` in [0, 1] are the longitudinal and azimuthal roughness.
+pub struct Hair {
+    sigma_a: Color,
+    beta_m: f64,
+    beta_n: f64,
+}
+
+impl Hair {
+    /// Natural hair from its concentrations of eumelanin, which browns and blackens it, and
+    /// pheomelanin, which reddens it. Eumelanin alone runs from about 0.3 for blonde through 1.3
+    /// for brown to 8 for black.
+    pub fn from_melanin(eumelanin: f64, pheomelanin: f64, beta_m: f64, beta_n: f64) -> Self {
+        let eumelanin_sigma_a = Color::with_values(0.419, 0.697, 1.37);
+        let pheomelanin_sigma_a = Color::with_values(0.187, 0.4, 1.05);
+        let sigma_a = eumelanin.max(0.0) * eumelanin_sigma_a + pheomelanin.max(0.0) * pheomelanin_sigma_a;
+        Self { sigma_a, beta_m, beta_n }
+    }
+
+    /// Hair of roughly the given multiple-scattering color, as for dyed hair, inverting the fit of
+    /// Chiang et al. for the absorption it takes.
+    pub fn from_color(color: Color, beta_m: f64, beta_n: f64) -> Self {
+        let b = beta_n;
+        let denominator = 5.969 - 0.215 * b + 2.532 * b.powi(2) - 10.73 * b.powi(3) + 5.574 * b.powi(4) + 0.245 * b.powi(5);
+        let absorption = |c: f64| (c.clamp(1e-4, 1.0).ln() / denominator).powi(2);
+        let sigma_a = Color::with_values(absorption(color.x()), absorption(color.y()), absorption(color.z()));
+        Self { sigma_a, beta_m, beta_n }
+    }
+}
+
+impl Material for Hair {
+    fn bsdf(&self, hit_record: &HitRecord, _context: &PathContext) -> Option<Bsdf> {
+        Some(Bsdf::new(hit_record, HairBxdf::new(self.sigma_a, self.beta_m, self.beta_n)))
+    }
+}
+
+/// The hair BSDF in a frame with x along the fiber. Angles θ are longitudinal, measured from the
+/// plane normal to the fiber, and φ azimuthal, around the fiber in that plane.
+struct HairBxdf {
+    sigma_a: Color,
+    /// Longitudinal variance of each lobe.
+    v: [f64; P_MAX + 1],
+    /// Azimuthal logistic scale.
+    s: f64,
+    /// sin and cos of 2^k times the scale angle, for k = 0, 1, 2.
+    sin_2k_alpha: [f64; 3],
+    cos_2k_alpha: [f64; 3],
+}
+
+/// What `HairBxdf` derives from the outgoing direction alone.
+struct Outgoing {
+    sin_theta: f64,
+    cos_theta: f64,
+    phi: f64,
+    gamma_o: f64,
+    gamma_t: f64,
+    /// Attenuation of each lobe.
+    ap: [Color; P_MAX + 1],
+}
+
+impl HairBxdf {
+    fn new(sigma_a: Color, beta_m: f64, beta_n: f64) -> Self {
+        let beta_m = beta_m.clamp(0.01, 1.0);
+        let beta_n = beta_n.clamp(0.01, 1.0);
+
+        let v0 = (0.726 * beta_m + 0.812 * beta_m * beta_m + 3.7 * beta_m.powi(20)).powi(2);
+        let v = [v0, 0.25 * v0, 4.0 * v0, 4.0 * v0];
+        let s = f64::sqrt(PI / 8.0) * (0.265 * beta_n + 1.194 * beta_n * beta_n + 5.372 * beta_n.powi(22));
+
+        let mut sin_2k_alpha = [SCALE_ANGLE.to_radians().sin(); 3];
+        let mut cos_2k_alpha = [safe_sqrt(1.0 - sin_2k_alpha[0] * sin_2k_alpha[0]); 3];
+        for i in 1..3 {
+            sin_2k_alpha[i] = 2.0 * cos_2k_alpha[i - 1] * sin_2k_alpha[i - 1];
+            cos_2k_alpha[i] = cos_2k_alpha[i - 1] * cos_2k_alpha[i - 1] - sin_2k_alpha[i - 1] * sin_2k_alpha[i - 1];
+        }
+
+        Self { sigma_a, v, s, sin_2k_alpha, cos_2k_alpha }
+    }
+
+    fn outgoing(&self, wo: Vec3) -> Outgoing {
+        let sin_theta = wo.x().clamp(-1.0, 1.0);
+        let cos_theta = safe_sqrt(1.0 - sin_theta * sin_theta);
+        let phi = f64::atan2(wo.z(), wo.y());
+
+        // Seen down the fiber, the normal (+z) sits at φ_o - γ_o, which gives the offset h = sin γ_o.
+        let perpendicular = f64::hypot(wo.y(), wo.z());
+        let h = if perpendicular > 0.0 { -wo.y() / perpendicular } else { 0.0 };
+        let gamma_o = h.clamp(-1.0, 1.0).asin();
+
+        // The refracted ray, with the modified index of the fiber's cross section (Bravais).
+        let sin_theta_t = sin_theta / ETA;
+        let cos_theta_t = safe_sqrt(1.0 - sin_theta_t * sin_theta_t);
+        let eta_p = safe_sqrt(ETA * ETA - sin_theta * sin_theta) / cos_theta;
+        let sin_gamma_t = (h / eta_p).clamp(-1.0, 1.0);
+        let cos_gamma_t = safe_sqrt(1.0 - sin_gamma_t * sin_gamma_t);
+        let gamma_t = sin_gamma_t.asin();
+
+        let length = 2.0 * cos_gamma_t / cos_theta_t;
+        let transmittance = Color::with_values(
+            f64::exp(-self.sigma_a.x() * length),
+            f64::exp(-self.sigma_a.y() * length),
+            f64::exp(-self.sigma_a.z() * length),
+        );
+
+        let f = fr_dielectric(cos_theta * gamma_o.cos(), ETA);
+        let mut ap = [Color::new(); P_MAX + 1];
+        ap[0] = Color::with_values(f, f, f);
+        ap[1] = (1.0 - f) * (1.0 - f) * transmittance;
+        for p in 2..P_MAX {
+            ap[p] = f * ap[p - 1] * transmittance;
+        }
+        // The geometric series of everything past the modeled lobes.
+        let rest = |a: f64, t: f64| if 1.0 - f * t > 0.0 { a * f * t / (1.0 - f * t) } else { 0.0 };
+        ap[P_MAX] = Color::with_values(
+            rest(ap[P_MAX - 1].x(), transmittance.x()),
+            rest(ap[P_MAX - 1].y(), transmittance.y()),
+            rest(ap[P_MAX - 1].z(), transmittance.z()),
+        );
+
+        Outgoing { sin_theta, cos_theta, phi, gamma_o, gamma_t, ap }
+    }
+
+    /// θ_o tilted by the scales for lobe `p`: R reflects off the tilted surface, TT and TRT refract
+    /// through it in turn.
+    fn tilt(&self, p: usize, sin_theta_o: f64, cos_theta_o: f64) -> (f64, f64) {
+        let (sin_theta, cos_theta) = match p {
+            0 => (
+                sin_theta_o * self.cos_2k_alpha[1] - cos_theta_o * self.sin_2k_alpha[1],
+                cos_theta_o * self.cos_2k_alpha[1] + sin_theta_o * self.sin_2k_alpha[1],
+            ),
+            1 => (
+                sin_theta_o * self.cos_2k_alpha[0] + cos_theta_o * self.sin_2k_alpha[0],
+                cos_theta_o * self.cos_2k_alpha[0] - sin_theta_o * self.sin_2k_alpha[0],
+            ),
+            2 => (
+                sin_theta_o * self.cos_2k_alpha[2] + cos_theta_o * self.sin_2k_alpha[2],
+                cos_theta_o * self.cos_2k_alpha[2] - sin_theta_o * self.sin_2k_alpha[2],
+            ),
+            _ => (sin_theta_o, cos_theta_o),
+        };
+        (sin_theta, cos_theta.abs())
+    }
+
+    /// The probability of sampling each lobe, in proportion to its mean attenuation.
+    fn lobe_pdfs(ap: &[Color; P_MAX + 1]) -> [f64; P_MAX + 1] {
+        let mean = |c: Color| (c.x() + c.y() + c.z()) / 3.0;
+        let total: f64 = ap.iter().map(|&a| mean(a)).sum();
+        ap.map(|a| if total > 0.0 { mean(a) / total } else { 0.0 })
+    }
+}
+
+impl Bxdf for HairBxdf {
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Color {
+        let o = self.outgoing(wo);
+        let sin_theta_i = wi.x().clamp(-1.0, 1.0);
+        let cos_theta_i = safe_sqrt(1.0 - sin_theta_i * sin_theta_i);
+        let phi = f64::atan2(wi.z(), wi.y()) - o.phi;
+
+        let mut f = Color::new();
+        for p in 0..P_MAX {
+            let (sin_theta_op, cos_theta_op) = self.tilt(p, o.sin_theta, o.cos_theta);
+            let m = mp(cos_theta_i, cos_theta_op, sin_theta_i, sin_theta_op, self.v[p]);
+            f += m * np(phi, p, self.s, o.gamma_o, o.gamma_t) * o.ap[p];
+        }
+        f += mp(cos_theta_i, o.cos_theta, sin_theta_i, o.sin_theta, self.v[P_MAX]) / (2.0 * PI) * o.ap[P_MAX];
+
+        // The model gives radiance scattered per projected solid angle; undo the cosine the
+        // integrator applies.
+        if wi.z() != 0.0 {
+            f /= wi.z().abs();
+        }
+        f
+    }
+
+    fn sample(&self, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        let o = self.outgoing(wo);
+        let lobe_pdfs = Self::lobe_pdfs(&o.ap);
+
+        // Pick a lobe, reusing what is left of u[0] for its azimuth.
+        let mut uc = u[0];
+        let mut p = 0;
+        while p < P_MAX && uc >= lobe_pdfs[p] {
+            uc -= lobe_pdfs[p];
+            p += 1;
+        }
+        if lobe_pdfs[p] == 0.0 {
+            return None;
+        }
+        let uc = (uc / lobe_pdfs[p]).min(1.0 - f64::EPSILON);
+
+        // Longitudinal angle around the lobe's tilted θ_o, by d'Eon et al.'s sampling of M_p.
+        let (sin_theta_op, cos_theta_op) = self.tilt(p, o.sin_theta, o.cos_theta);
+        let v = self.v[p];
+        let u1 = u[1].max(1e-5);
+        let cos_theta = 1.0 + v * f64::ln(u1 + (1.0 - u1) * f64::exp(-2.0 / v));
+        let sin_theta = safe_sqrt(1.0 - cos_theta * cos_theta);
+        let cos_phi = (2.0 * PI * u[2]).cos();
+        let sin_theta_i = (-cos_theta * sin_theta_op + sin_theta * cos_phi * cos_theta_op).clamp(-1.0, 1.0);
+        let cos_theta_i = safe_sqrt(1.0 - sin_theta_i * sin_theta_i);
+
+        let delta_phi = if p < P_MAX {
+            phi_p(p, o.gamma_o, o.gamma_t) + sample_trimmed_logistic(uc, self.s, -PI, PI)
+        } else {
+            2.0 * PI * uc
+        };
+        let phi_i = o.phi + delta_phi;
+        let wi = Vec3::with_values(sin_theta_i, cos_theta_i * phi_i.cos(), cos_theta_i * phi_i.sin());
+
+        Some(BsdfSample {
+            f: self.eval(wo, wi),
+            wi,
+            pdf: self.pdf(wo, wi),
+            flags: BsdfFlags::REFLECTION | BsdfFlags::GLOSSY,
+            eta: 1.0,
+        })
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> f64 {
+        let o = self.outgoing(wo);
+        let lobe_pdfs = Self::lobe_pdfs(&o.ap);
+        let sin_theta_i = wi.x().clamp(-1.0, 1.0);
+        let cos_theta_i = safe_sqrt(1.0 - sin_theta_i * sin_theta_i);
+        let phi = f64::atan2(wi.z(), wi.y()) - o.phi;
+
+        let mut pdf = 0.0;
+        for (p, lobe_pdf) in lobe_pdfs.iter().enumerate().take(P_MAX) {
+            let (sin_theta_op, cos_theta_op) = self.tilt(p, o.sin_theta, o.cos_theta);
+            let m = mp(cos_theta_i, cos_theta_op, sin_theta_i, sin_theta_op, self.v[p]);
+            pdf += m * lobe_pdf * np(phi, p, self.s, o.gamma_o, o.gamma_t);
+        }
+        pdf + mp(cos_theta_i, o.cos_theta, sin_theta_i, o.sin_theta, self.v[P_MAX]) * lobe_pdfs[P_MAX] / (2.0 * PI)
+    }
+
+    fn flags(&self) -> BsdfFlags {
+        BsdfFlags::REFLECTION | BsdfFlags::GLOSSY
+    }
+}
+
+fn safe_sqrt(x: f64) -> f64 {
+    x.max(0.0).sqrt()
+}
+
+/// Longitudinal scattering of d'Eon et al. (2011), with variance `v`.
+fn mp(cos_theta_i: f64, cos_theta_o: f64, sin_theta_i: f64, sin_theta_o: f64, v: f64) -> f64 {
+    let a = cos_theta_i * cos_theta_o / v;
+    let b = sin_theta_i * sin_theta_o / v;
+    if v <= 0.1 {
+        // In log space, where the Bessel function and the sinh can't overflow.
+        f64::exp(log_i0(a) - b - 1.0 / v + std::f64::consts::LN_2 + f64::ln(1.0 / (2.0 * v)))
+    } else {
+        f64::exp(-b) * i0(a) / (f64::sinh(1.0 / v) * 2.0 * v)
+    }
+}
+
+/// Modified Bessel function of the first kind, order 0, by its series.
+fn i0(x: f64) -> f64 {
+    let mut sum = 0.0;
+    let mut x2i = 1.0;
+    let mut i_fact = 1.0;
+    let mut four_i = 1.0;
+    for i in 0..10 {
+        if i > 1 {
+            i_fact *= i as f64;
+        }
+        sum += x2i / (four_i * i_fact * i_fact);
+        x2i *= x * x;
+        four_i *= 4.0;
+    }
+    sum
+}
+
+fn log_i0(x: f64) -> f64 {
+    if x > 12.0 {
+        x + 0.5 * (-f64::ln(2.0 * PI) + f64::ln(1.0 / x) + 1.0 / (8.0 * x))
+    } else {
+        i0(x).ln()
+    }
+}
+
+/// The azimuthal direction lobe `p` leaves in relative to φ_o, for a perfectly smooth fiber.
+fn phi_p(p: usize, gamma_o: f64, gamma_t: f64) -> f64 {
+    2.0 * p as f64 * gamma_t - 2.0 * gamma_o + p as f64 * PI
+}
+
+/// Azimuthal scattering of lobe `p`: a logistic around `phi_p`, wrapped to [-π, π].
+fn np(phi: f64, p: usize, s: f64, gamma_o: f64, gamma_t: f64) -> f64 {
+    let mut delta_phi = phi - phi_p(p, gamma_o, gamma_t);
+    while delta_phi > PI {
+        delta_phi -= 2.0 * PI;
+    }
+    while delta_phi < -PI {
+        delta_phi += 2.0 * PI;
+    }
+    trimmed_logistic(delta_phi, s, -PI, PI)
+}
+
+fn logistic(x: f64, s: f64) -> f64 {
+    let x = x.abs();
+    let e = f64::exp(-x / s);
+    e / (s * (1.0 + e) * (1.0 + e))
+}
+
+fn logistic_cdf(x: f64, s: f64) -> f64 {
+    1.0 / (1.0 + f64::exp(-x / s))
+}
+
+/// The logistic distribution restricted to [a, b] and renormalized.
+fn trimmed_logistic(x: f64, s: f64, a: f64, b: f64) -> f64 {
+    logistic(x, s) / (logistic_cdf(b, s) - logistic_cdf(a, s))
+}
+
+fn sample_trimmed_logistic(u: f64, s: f64, a: f64, b: f64) -> f64 {
+    let k = logistic_cdf(b, s) - logistic_cdf(a, s);
+    let x = -s * f64::ln(1.0 / (u * k + logistic_cdf(a, s)) - 1.0);
+    x.clamp(a, b)
+}
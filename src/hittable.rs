@@ -1,23 +1,202 @@
 use std::sync::Arc;
 
-use crate::{interval::Interval, material::Material, ray::{Point3, Ray}, vec3::{dot, Vec3}};
+use crate::{interval::Interval, material::Material, ray::{Point3, Ray, RayDifferential}, vec3::{self, abs, dot, unit_vector, Vec3}};
+
+/// Screen-space derivatives of the hit point and of its (u, v), i.e. how far they move from one pixel
+/// to the next. All zero when the incoming ray carried no differentials.
+#[derive(Clone, Copy, Default)]
+pub struct Footprint {
+    pub dpdx: Vec3,
+    pub dpdy: Vec3,
+    pub dudx: f64,
+    pub dudy: f64,
+    pub dvdx: f64,
+    pub dvdy: f64,
+}
 
 #[derive(Clone)]
 pub struct HitRecord {
     pub p: Point3,
+    /// Conservative per-component bound on the absolute floating-point error of `p`.
+    pub p_error: Vec3,
+    /// Geometric normal, facing the incoming ray. Used to tell the two sides of the surface apart and
+    /// to offset spawned rays, so it must never be perturbed.
     pub normal: Vec3,
+    /// Normal the material shades with, on the same side as `normal`. Starts out equal to it and may
+    /// be bent by normal or bump maps.
+    pub shading_normal: Vec3,
     pub mat: Arc<dyn Material>,
     pub t: f64,
+    pub u: f64,
+    pub v: f64,
+    /// Partial derivatives of `p` and of `normal` with respect to (u, v).
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
+    pub dndu: Vec3,
+    pub dndv: Vec3,
+    pub footprint: Footprint,
     pub front_face: bool,
+    /// Whether the surface is one of the lights the camera samples directly, i.e. was added with
+    /// `HittableList::add_light`.
+    pub light: bool,
 }
 
 impl HitRecord {
     pub fn set_face_normal(&mut self, ray: &Ray, outward_normal: Vec3) {
         self.front_face = dot(&ray.direction(), &outward_normal) < 0.0;
         self.normal = if self.front_face { outward_normal } else { -outward_normal };
+        self.shading_normal = self.normal;
+        if !self.front_face {
+            self.dndu = -self.dndu;
+            self.dndv = -self.dndv;
+        }
+    }
+
+    /// Replaces the shading normal with `outward` (given on the outside of the surface, like the normal
+    /// passed to `set_face_normal`), flipping it to the side the ray came from.
+    pub fn set_shading_normal(&mut self, outward: Vec3) {
+        let n = unit_vector(&outward);
+        self.shading_normal = if self.front_face { n } else { -n };
+    }
+
+    /// Shading normal on the outside of the surface, whichever side the ray came from.
+    pub fn outward_shading_normal(&self) -> Vec3 {
+        if self.front_face { self.shading_normal } else { -self.shading_normal }
+    }
+
+    /// Fills `footprint` by intersecting the offset rays of `ray` with the tangent plane at `p`, then
+    /// solving for the (u, v) change that best explains the offsets (pbrt, section 10.1.1).
+    pub fn compute_footprint(&mut self, ray: &Ray) {
+        self.footprint = Footprint::default();
+        let Some(differential) = ray.differential() else {
+            return;
+        };
+
+        let d = dot(&self.normal, &self.p);
+        let tx = (d - dot(&self.normal, &differential.rx_origin)) / dot(&self.normal, &differential.rx_direction);
+        let ty = (d - dot(&self.normal, &differential.ry_origin)) / dot(&self.normal, &differential.ry_direction);
+        if !tx.is_finite() || !ty.is_finite() {
+            return;
+        }
+
+        let dpdx = differential.rx_origin + tx * differential.rx_direction - self.p;
+        let dpdy = differential.ry_origin + ty * differential.ry_direction - self.p;
+
+        let ata00 = dot(&self.dpdu, &self.dpdu);
+        let ata01 = dot(&self.dpdu, &self.dpdv);
+        let ata11 = dot(&self.dpdv, &self.dpdv);
+        let mut inv_det = 1.0 / (ata00 * ata11 - ata01 * ata01);
+        if !inv_det.is_finite() {
+            inv_det = 0.0;
+        }
+
+        let atb0x = dot(&self.dpdu, &dpdx);
+        let atb1x = dot(&self.dpdv, &dpdx);
+        let atb0y = dot(&self.dpdu, &dpdy);
+        let atb1y = dot(&self.dpdv, &dpdy);
+
+        let clamp = |x: f64| if x.is_finite() { x.clamp(-1e8, 1e8) } else { 0.0 };
+        self.footprint = Footprint {
+            dpdx,
+            dpdy,
+            dudx: clamp((ata11 * atb0x - ata01 * atb1x) * inv_det),
+            dvdx: clamp((ata00 * atb1x - ata01 * atb0x) * inv_det),
+            dudy: clamp((ata11 * atb0y - ata01 * atb1y) * inv_det),
+            dvdy: clamp((ata00 * atb1y - ata01 * atb0y) * inv_det),
+        };
+    }
+
+    /// Differentials for a perfect mirror bounce: each offset ray is reflected at its own offset point
+    /// about the normal as it varies across the footprint.
+    pub fn reflected_differential(&self, ray_in: &Ray) -> Option<RayDifferential> {
+        let differential = ray_in.differential()?;
+        let (nx, ny) = self.offset_normals();
+
+        Some(RayDifferential {
+            rx_origin: self.p + self.footprint.dpdx,
+            rx_direction: vec3::reflect(differential.rx_direction, nx),
+            ry_origin: self.p + self.footprint.dpdy,
+            ry_direction: vec3::reflect(differential.ry_direction, ny),
+        })
+    }
+
+    /// Differentials for a refraction with the same ratio of indices used for the main ray. Returns
+    /// `None` when either offset ray would be totally internally reflected.
+    pub fn refracted_differential(&self, ray_in: &Ray, etai_over_etat: f64) -> Option<RayDifferential> {
+        let differential = ray_in.differential()?;
+        let (nx, ny) = self.offset_normals();
+
+        let refract = |direction: Vec3, n: Vec3| {
+            let cos_theta = f64::min(dot(&-direction, &n), 1.0);
+            if etai_over_etat * f64::sqrt(1.0 - cos_theta * cos_theta) > 1.0 {
+                return None;
+            }
+            Some(unit_vector(&vec3::refract(direction, n, etai_over_etat)))
+        };
+
+        Some(RayDifferential {
+            rx_origin: self.p + self.footprint.dpdx,
+            rx_direction: refract(differential.rx_direction, nx)?,
+            ry_origin: self.p + self.footprint.dpdy,
+            ry_direction: refract(differential.ry_direction, ny)?,
+        })
+    }
+
+    fn offset_normals(&self) -> (Vec3, Vec3) {
+        let dndx = self.footprint.dudx * self.dndu + self.footprint.dvdx * self.dndv;
+        let dndy = self.footprint.dudy * self.dndu + self.footprint.dvdy * self.dndv;
+        (unit_vector(&(self.shading_normal + dndx)), unit_vector(&(self.shading_normal + dndy)))
+    }
+
+    /// Ray leaving the hit point in `direction`, with the origin offset along the normal by just enough
+    /// to clear `p_error`, so it can not re-intersect the surface it starts on.
+    pub fn spawn_ray(&self, direction: Vec3) -> Ray {
+        Ray::new(self.offset_origin(direction), direction)
+    }
+
+    /// Like `spawn_ray`, carrying the given differentials along.
+    pub fn spawn_ray_with_differential(&self, direction: Vec3, differential: Option<RayDifferential>) -> Ray {
+        Ray::with_differential(self.offset_origin(direction), direction, differential)
+    }
+
+    fn offset_origin(&self, direction: Vec3) -> Point3 {
+        let n = self.normal;
+        let d = dot(&abs(n), &self.p_error);
+        let mut offset = d * n;
+        if dot(&direction, &n) < 0.0 {
+            offset = -offset;
+        }
+
+        let mut po = self.p + offset;
+        // Round away from p so the addition above can't land the origin back inside the error box.
+        for i in 0..3 {
+            if offset[i] > 0.0 {
+                po[i] = po[i].next_up();
+            } else if offset[i] < 0.0 {
+                po[i] = po[i].next_down();
+            }
+        }
+        po
     }
 }
 
 pub trait Hittable: Send + Sync {
     fn hit(&self, ray: &Ray, interval: Interval) -> Option<HitRecord>;
+
+    /// Any-hit query: returns true as soon as some intersection inside `interval` is found.
+    /// Unlike `hit`, it never looks for the closest hit and never builds a `HitRecord`,
+    /// which makes it the cheap choice for shadow rays.
+    fn occluded(&self, ray: &Ray, interval: Interval) -> bool;
+
+    /// Density, per unit solid angle around `origin`, with which `sample` picks `direction`. Only
+    /// shapes that can be sampled as lights override it; everything else is never picked.
+    fn pdf_value(&self, _origin: Point3, _direction: Vec3) -> f64 {
+        0.0
+    }
+
+    /// Direction from `origin` toward a random point of the shape, not necessarily normalized, for
+    /// sampling the shape as a light.
+    fn sample(&self, _origin: Point3) -> Vec3 {
+        Vec3::with_values(1.0, 0.0, 0.0)
+    }
 }
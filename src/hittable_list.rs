@@ -1,4 +1,6 @@
-use crate::{hittable::{HitRecord, Hittable}, interval::Interval, ray::Ray};
+use std::sync::Arc;
+
+use crate::{hittable::{HitRecord, Hittable}, interval::Interval, ray::{Point3, Ray}, utils::random_float, vec3::Vec3};
 
 pub struct HittableList {
     pub objects: Vec<Box<dyn Hittable>>,
@@ -12,6 +14,37 @@ impl HittableList {
     pub fn add(&mut self, object: Box<dyn Hittable>) {
         self.objects.push(object);
     }
+
+    /// Adds an emitter to the scene and to `lights`, the list the camera samples directly. Both hold
+    /// the same object, and hits on it in the scene are flagged as lights.
+    pub fn add_light(&mut self, lights: &mut HittableList, object: Box<dyn Hittable>) {
+        let object: Arc<dyn Hittable> = Arc::from(object);
+        self.add(Box::new(Light { object: object.clone() }));
+        lights.add(Box::new(Light { object }));
+    }
+}
+
+/// An object shared between the scene and its list of lights.
+struct Light {
+    object: Arc<dyn Hittable>,
+}
+
+impl Hittable for Light {
+    fn hit(&self, ray: &Ray, interval: Interval) -> Option<HitRecord> {
+        self.object.hit(ray, interval).map(|hit_record| HitRecord { light: true, ..hit_record })
+    }
+
+    fn occluded(&self, ray: &Ray, interval: Interval) -> bool {
+        self.object.occluded(ray, interval)
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        self.object.pdf_value(origin, direction)
+    }
+
+    fn sample(&self, origin: Point3) -> Vec3 {
+        self.object.sample(origin)
+    }
 }
 
 impl Hittable for HittableList {
@@ -28,4 +61,21 @@ impl Hittable for HittableList {
 
        temp_rec 
     }
+
+    fn occluded(&self, ray: &Ray, interval: Interval) -> bool {
+        self.objects
+            .iter()
+            .any(|object| object.occluded(ray, interval))
+    }
+
+    /// Picks one of the objects uniformly and samples it, so the density is the average of theirs.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let weight = 1.0 / self.objects.len() as f64;
+        self.objects.iter().map(|object| weight * object.pdf_value(origin, direction)).sum()
+    }
+
+    fn sample(&self, origin: Point3) -> Vec3 {
+        let index = ((random_float() * self.objects.len() as f64) as usize).min(self.objects.len() - 1);
+        self.objects[index].sample(origin)
+    }
 }
@@ -1,4 +1,5 @@
 
+#[derive(Clone, Copy)]
 pub struct Interval {
     pub min: f64,
     pub max: f64,
@@ -31,8 +32,10 @@ impl Interval {
         x
     }
 
-    pub const UNIVERSE: Interval = Interval {
-        min: f64::NEG_INFINITY,
+    /// Everything strictly in front of a ray origin. Origins produced by `HitRecord::spawn_ray` are
+    /// already pushed clear of their surface, so no epsilon is needed here.
+    pub const POSITIVE: Interval = Interval {
+        min: 0.0,
         max: f64::INFINITY,
     };
 }
@@ -1,89 +1,60 @@
-use std::sync::Arc;
-
-use crate::{camera::Camera, color::Color, hittable_list::HittableList, material::{Dielectric, Lambertian, Metal}, ray::Point3, sphere::Sphere, utils::{random_float, random_float_range}, vec3::Vec3};
-
+mod alpha_cutout;
+mod bsdf;
 mod camera;
+mod cloth;
 mod color;
+mod fresnel;
+mod hair;
 mod hittable;
 mod hittable_list;
 mod interval;
 mod material;
+mod measured;
+mod medium;
+mod microfacet;
+mod mipmap;
+mod noise;
+mod principled;
+mod projection;
+mod quad;
 mod ray;
+mod sampling;
+mod rgb_to_spectrum;
+mod scenes;
+mod shader_graph;
+mod spectrum;
 mod sphere;
+mod texture;
+mod texture_cache;
+mod triangle;
 mod utils;
 mod vec3;
 
-const OBJS_RANGE: i32 = 22;
-
 fn main() {
-    let material_ground = Arc::new(Lambertian::new(Color::with_values(0.5, 0.5, 0.5)));
-
-    let mut world = HittableList::new();
-
-    world.add(Box::new(Sphere::new(
-        Point3::with_values(0.0, -1000., 0.0),
-        1000.0,
-        material_ground,
-    )));
-
-    for obj_a in -OBJS_RANGE/2..OBJS_RANGE/2 {
-        for obj_b in -OBJS_RANGE/2..OBJS_RANGE/2 {
-            let choose_mat = random_float();
-            let center = Point3::with_values(obj_a as f64 + 0.9*random_float(), 0.2, obj_b as f64 + 0.9*random_float());
-            if (center - Point3::with_values(4.0, 0.2, 0.0)).length() > 0.9 {
-                if choose_mat < 0.8 {
-                    let albedo = Color::random() * Color::random();
-                    let sphere_material = Arc::new(Lambertian::new(albedo));
-                    world.add(Box::new(Sphere::new(center, 0.2, sphere_material)));
-                } else if choose_mat < 0.95 {
-                    let albedo = Color::random_range(0.5, 1.0);
-                    let fuzz = random_float_range(0.0, 0.5);
-                    let sphere_material = Arc::new(Metal::new(albedo, fuzz));
-                    world.add(Box::new(Sphere::new(center, 0.2, sphere_material)));
-                } else {
-                    let sphere_material = Arc::new(Dielectric::new(1.5));
-                    world.add(Box::new(Sphere::new(center, 0.2, sphere_material)));
-                }
-            }
-
-        }
+    match std::env::args().nth(1).as_deref() {
+        Some("checkered_spheres") => scenes::checkered_spheres(),
+        Some("earth") => scenes::earth(),
+        Some("texture_filtering") => scenes::texture_filtering(),
+        Some("procedural") => scenes::procedural(),
+        Some("surface_detail") => scenes::surface_detail(),
+        Some("projections") => scenes::projections(),
+        Some("udim") => scenes::udim(),
+        Some("shader_graph") => scenes::shader_graph(std::env::args().nth(2)),
+        Some("metals") => scenes::metals(),
+        Some("glass") => scenes::glass(),
+        Some("nested_dielectrics") => scenes::nested_dielectrics(),
+        Some("dispersion") => scenes::dispersion(std::env::args().nth(2).as_deref() == Some("spectral")),
+        Some("principled") => scenes::principled(),
+        Some("iridescence") => scenes::iridescence(),
+        Some("coated") => scenes::coated(),
+        Some("subsurface") => scenes::subsurface(),
+        Some("cutout") => scenes::cutout(),
+        Some("cloth_and_hair") => scenes::cloth_and_hair(),
+        Some("measured") => scenes::measured(std::env::args().nth(2)),
+        Some("mis") => scenes::mis(),
+        Some("cornell_box") => scenes::cornell_box(),
+        _ => scenes::bouncing_spheres(),
     }
 
-    let material1 = Arc::new(Dielectric::new(1.5));
-    world.add(Box::new(Sphere::new(
-        Point3::with_values(0.0, 1.0, 0.0),
-        1.0,
-        material1,
-    )));
-
-    let material2 = Arc::new(Lambertian::new(Color::with_values(0.4, 0.2, 0.1)));
-    world.add(Box::new(Sphere::new(
-        Point3::with_values(-4.0, 1.0, 0.0),
-        1.0,
-        material2,
-    )));
-
-    let material3 = Arc::new(Metal::new(Color::with_values(0.7, 0.6, 0.5), 0.0));
-    world.add(Box::new(Sphere::new(
-        Point3::with_values(4.0, 1.0, 0.0),
-        1.0,
-        material3,
-    )));
-
-
-    let mut cam = Camera::default();
-    cam.aspect_ratio = 16.0 / 9.0;
-    cam.image_width = 1200;
-    cam.samples_per_pixel = 500;
-    cam.max_depth = 50;
-    cam.look_from = Point3::with_values(13.0, 2.0, 3.0);
-    cam.look_at = Point3::with_values(0.0, 0.0, 0.0);
-    cam.vup = Vec3::with_values(0.0, 1.0, 0.0);
-    cam.vfov = 20.0;
-    cam.defocus_angle = 0.6;
-    cam.focus_distance = 10.0;
-
-    cam.render(&world);
-
     println!("Image saved as output.jpg");
 }
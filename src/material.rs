@@ -1,109 +1,472 @@
-use crate::{color::Color, hittable::HitRecord, ray::Ray, utils::random_float, vec3::{self, random_unit_vector}};
+use std::sync::Arc;
 
-pub struct ScatterRecord {
-    pub attenuation: Color,
-    pub scattered: Ray,
+use crate::{bsdf::{Bsdf, DielectricBxdf, DiffuseBxdf, Frame, FuzzyMetalBxdf, LayeredBxdf, MicrofacetReflectionBxdf, RoughDielectricBxdf}, color::Color, fresnel::{ComplexIor, FilmLayer, Fresnel}, hittable::HitRecord, medium::{Ior, Medium}, microfacet::TrowbridgeReitz, ray::Point3, texture::{SolidColor, Texture, TextureContext}, utils::random_float, vec3};
+
+/// What the path knows when it reaches a surface, beyond the hit itself.
+#[derive(Clone, Copy)]
+pub struct PathContext {
+    /// Index of refraction on the far side of the surface from the material's interior medium, at
+    /// `wavelength`.
+    pub exterior_ior: f64,
+    /// The wavelength the path samples, in nanometers, for anything that disperses light.
+    pub wavelength: f64,
 }
 
 pub trait Material: Send + Sync {
-    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord>;
+    /// The scattering function at a hit, or `None` for surfaces that don't scatter light at all.
+    fn bsdf(&self, hit_record: &HitRecord, context: &PathContext) -> Option<Bsdf>;
+
+    /// The medium the surface encloses, for materials that bound one.
+    fn interior(&self) -> Option<Medium> {
+        None
+    }
+
+    /// Radiance emitted by the surface at `p`. Only lights emit, so the default is black.
+    fn emitted(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        Color::new()
+    }
 }
 
 pub struct Lambertian {
-    albedo: Color,
+    albedo: Arc<dyn Texture>,
 }
 
 impl Lambertian {
     pub fn new(albedo: Color) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn from_texture(albedo: Arc<dyn Texture>) -> Self {
         Self { albedo }
     }
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
-        let mut scatter_direction = hit_record.normal + random_unit_vector();
-
-        if scatter_direction.near_zero() {
-            scatter_direction = hit_record.normal;
-        }
-
-        Some(ScatterRecord {
-            attenuation: self.albedo,
-            scattered: Ray::new(hit_record.p, scatter_direction),
-        })
+    fn bsdf(&self, hit_record: &HitRecord, _context: &PathContext) -> Option<Bsdf> {
+        let albedo = self.albedo.value(&TextureContext::from_hit(hit_record));
+        Some(Bsdf::new(hit_record, DiffuseBxdf::new(albedo)))
     }
 }
 
 pub struct Metal {
-    albedo: Color,
-    fuzz: f64,
+    albedo: Arc<dyn Texture>,
+    fuzz: Arc<dyn Texture>,
+    thin_film: Option<ThinFilm>,
 }
 
 impl Metal {
     pub fn new(albedo: Color, fuzz: f64) -> Self {
-        Self {
-            albedo,
-            fuzz: if fuzz < 1.0 { 1.0 } else { fuzz }
-        }
+        Self::from_texture(Arc::new(SolidColor::new(albedo)), fuzz)
+    }
+
+    pub fn from_texture(albedo: Arc<dyn Texture>, fuzz: f64) -> Self {
+        Self::from_textures(albedo, Arc::new(SolidColor::gray(fuzz.clamp(0.0, 1.0))))
+    }
+
+    /// Metal with a spatially varying fuzz, read through `Texture::scalar` and capped at 1.
+    pub fn from_textures(albedo: Arc<dyn Texture>, fuzz: Arc<dyn Texture>) -> Self {
+        Self { albedo, fuzz, thin_film: None }
+    }
+
+    /// Metal under a thin film, such as heat-tinted steel; the albedo is the bare metal's color and
+    /// `fuzz` is clamped to [0, 1] as in `from_texture`, so a glossy film stays glossy.
+    pub fn with_thin_film(albedo: Color, fuzz: f64, thin_film: ThinFilm) -> Self {
+        Self { thin_film: Some(thin_film), ..Self::from_texture(Arc::new(SolidColor::new(albedo)), fuzz) }
     }
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
-        let reflected = vec3::reflect(vec3::unit_vector(&ray_in.direction()), hit_record.normal);
-        let scattered = Ray::new(hit_record.p, reflected + self.fuzz * vec3::random_unit_vector());
-
-        if vec3::dot(&scattered.direction(), &hit_record.normal) > 0.0 {
-            Some(ScatterRecord {
-                attenuation: self.albedo,
-                scattered,
-            })
-        } else {
-            None
+    fn bsdf(&self, hit_record: &HitRecord, context: &PathContext) -> Option<Bsdf> {
+        let ctx = TextureContext::from_hit(hit_record);
+        let fuzz = self.fuzz.scalar(&ctx).clamp(0.0, 1.0);
+        let albedo = self.albedo.value(&ctx);
+        let bxdf = match &self.thin_film {
+            Some(thin_film) => FuzzyMetalBxdf::with_thin_film(albedo, fuzz, thin_film.layer(&ctx, context)),
+            None => FuzzyMetalBxdf::new(albedo, fuzz),
+        };
+        Some(Bsdf::new(hit_record, bxdf))
+    }
+}
+
+/// A thin transparent film over a `Dielectric`, `Conductor` or `Metal`, whose thickness in
+/// nanometers and index of refraction are read through `Texture::scalar`. Soap films run a few
+/// hundred nanometers thick with an index near 1.33; interference colors fade out past a micron or so.
+pub struct ThinFilm {
+    thickness: Arc<dyn Texture>,
+    index_of_refraction: Arc<dyn Texture>,
+}
+
+impl ThinFilm {
+    pub fn new(thickness: f64, index_of_refraction: f64) -> Self {
+        Self::from_textures(Arc::new(SolidColor::gray(thickness)), Arc::new(SolidColor::gray(index_of_refraction)))
+    }
+
+    pub fn from_textures(thickness: Arc<dyn Texture>, index_of_refraction: Arc<dyn Texture>) -> Self {
+        Self { thickness, index_of_refraction }
+    }
+
+    fn layer(&self, ctx: &TextureContext, context: &PathContext) -> FilmLayer {
+        FilmLayer {
+            thickness: self.thickness.scalar(ctx).max(0.0),
+            index_of_refraction: self.index_of_refraction.scalar(ctx) / context.exterior_ior,
         }
     }
 }
 
+/// A physically based metal: GGX microfacets over the Fresnel reflectance of a complex index of
+/// refraction. Roughness is perceptual, in [0, 1]; `roughness_u` runs along dpdu and `roughness_v`
+/// across it, so unequal values give a brushed look.
+pub struct Conductor {
+    ior: ComplexIor,
+    roughness_u: Arc<dyn Texture>,
+    roughness_v: Arc<dyn Texture>,
+    thin_film: Option<ThinFilm>,
+}
+
+impl Conductor {
+    pub fn new(ior: ComplexIor, roughness: f64) -> Self {
+        Self::anisotropic(ior, roughness, roughness)
+    }
+
+    pub fn anisotropic(ior: ComplexIor, roughness_u: f64, roughness_v: f64) -> Self {
+        Self::from_textures(
+            ior,
+            Arc::new(SolidColor::gray(roughness_u)),
+            Arc::new(SolidColor::gray(roughness_v)),
+        )
+    }
+
+    /// Conductor with spatially varying roughness, read through `Texture::scalar`.
+    pub fn from_textures(ior: ComplexIor, roughness_u: Arc<dyn Texture>, roughness_v: Arc<dyn Texture>) -> Self {
+        Self { ior, roughness_u, roughness_v, thin_film: None }
+    }
+
+    /// Conductor under a thin film, like anodized titanium or oil on steel.
+    pub fn with_thin_film(ior: ComplexIor, roughness: f64, thin_film: ThinFilm) -> Self {
+        Self { thin_film: Some(thin_film), ..Self::new(ior, roughness) }
+    }
+}
+
+impl Material for Conductor {
+    fn bsdf(&self, hit_record: &HitRecord, context: &PathContext) -> Option<Bsdf> {
+        let ctx = TextureContext::from_hit(hit_record);
+        let distribution = TrowbridgeReitz::new(
+            TrowbridgeReitz::roughness_to_alpha(self.roughness_u.scalar(&ctx)),
+            TrowbridgeReitz::roughness_to_alpha(self.roughness_v.scalar(&ctx)),
+        );
+        let fresnel = match &self.thin_film {
+            Some(thin_film) => Fresnel::ThinFilmConductor(thin_film.layer(&ctx, context), self.ior),
+            None => Fresnel::Conductor(self.ior),
+        };
+        Some(Bsdf::new(hit_record, MicrofacetReflectionBxdf::new(distribution, fresnel)))
+    }
+}
+
+/// Smooth glass, or any other clear or absorbing dielectric, bounding its `Medium`.
 pub struct Dielectric {
-    index_of_refraction: f64, // index of refraction
+    medium: Medium,
+    thin_film: Option<ThinFilm>,
 }
 
 impl Dielectric {
-    pub fn new(index_of_refraction: f64) -> Self {
-        Self {
-            index_of_refraction,
-        }
+    pub fn new(index_of_refraction: impl Into<Ior>) -> Self {
+        Self::with_medium(Medium::clear(index_of_refraction))
+    }
+
+    pub fn with_medium(medium: Medium) -> Self {
+        Self { medium, thin_film: None }
     }
 
-    fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
-        let mut r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
-        r0 = r0 * r0;
-        r0 + (1.0 - r0) * f64::powf(1.0 - cosine, 5.0)
+    /// A dielectric under a thin film: a soap bubble with a medium of index 1, or coated glass.
+    pub fn with_thin_film(medium: Medium, thin_film: ThinFilm) -> Self {
+        Self { medium, thin_film: Some(thin_film) }
     }
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
-        let refraction_ratio = if hit_record.front_face {
-            1.0 / self.index_of_refraction
-        } else {
-            self.index_of_refraction
+    fn bsdf(&self, hit_record: &HitRecord, context: &PathContext) -> Option<Bsdf> {
+        let index_of_refraction = self.medium.index_of_refraction.at(context.wavelength) / context.exterior_ior;
+        let bxdf = match &self.thin_film {
+            Some(thin_film) => {
+                let layer = thin_film.layer(&TextureContext::from_hit(hit_record), context);
+                DielectricBxdf::with_thin_film(index_of_refraction, layer)
+            }
+            None => DielectricBxdf::new(index_of_refraction),
         };
+        Some(Bsdf::new(hit_record, bxdf))
+    }
+
+    fn interior(&self) -> Option<Medium> {
+        Some(self.medium)
+    }
+}
+
+/// Frosted glass: `Dielectric` with a GGX microfacet surface. Roughness is perceptual, in [0, 1].
+pub struct RoughDielectric {
+    medium: Medium,
+    roughness: Arc<dyn Texture>,
+}
+
+impl RoughDielectric {
+    pub fn new(index_of_refraction: impl Into<Ior>, roughness: f64) -> Self {
+        Self::from_texture(index_of_refraction, Arc::new(SolidColor::gray(roughness)))
+    }
+
+    /// Rough glass with spatially varying roughness, read through `Texture::scalar`.
+    pub fn from_texture(index_of_refraction: impl Into<Ior>, roughness: Arc<dyn Texture>) -> Self {
+        Self::with_medium(Medium::clear(index_of_refraction), roughness)
+    }
+
+    pub fn with_medium(medium: Medium, roughness: Arc<dyn Texture>) -> Self {
+        Self { medium, roughness }
+    }
+}
+
+impl Material for RoughDielectric {
+    fn bsdf(&self, hit_record: &HitRecord, context: &PathContext) -> Option<Bsdf> {
+        let alpha = TrowbridgeReitz::roughness_to_alpha(self.roughness.scalar(&TextureContext::from_hit(hit_record)));
+        let distribution = TrowbridgeReitz::new(alpha, alpha);
+        let index_of_refraction = self.medium.index_of_refraction.at(context.wavelength) / context.exterior_ior;
+        Some(Bsdf::new(hit_record, RoughDielectricBxdf::new(index_of_refraction, distribution)))
+    }
+
+    fn interior(&self) -> Option<Medium> {
+        Some(self.medium)
+    }
+}
+
+/// Translucent matter like skin, wax, marble or milk: a `RoughDielectric` boundary around a medium
+/// that scatters light, which paths random walk through until they leave or get absorbed. `albedo`
+/// is the single-scattering albedo and `mean_free_path` the mean distance between scattering or
+/// absorption events, per channel, in scene units; longer paths make the object more translucent.
+pub struct Subsurface {
+    boundary: RoughDielectric,
+}
+
+impl Subsurface {
+    pub fn new(index_of_refraction: impl Into<Ior>, albedo: Color, mean_free_path: Color, roughness: f64) -> Self {
+        let medium = Medium::subsurface(index_of_refraction, albedo, mean_free_path);
+        Self { boundary: RoughDielectric::with_medium(medium, Arc::new(SolidColor::gray(roughness))) }
+    }
+}
+
+impl Material for Subsurface {
+    fn bsdf(&self, hit_record: &HitRecord, context: &PathContext) -> Option<Bsdf> {
+        self.boundary.bsdf(hit_record, context)
+    }
+
+    fn interior(&self) -> Option<Medium> {
+        self.boundary.interior()
+    }
+}
 
-        let unit_direction = vec3::unit_vector(&ray_in.direction());
-        let cos_theta = f64::min(vec3::dot(&-unit_direction, &hit_record.normal), 1.0);
-        let sin_theta = f64::sqrt(1.0 - cos_theta * cos_theta);
+pub struct DiffuseLight {
+    radiance: Arc<dyn Texture>,
+}
+
+impl DiffuseLight {
+    pub fn new(radiance: Color) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(radiance)))
+    }
+
+    pub fn from_texture(radiance: Arc<dyn Texture>) -> Self {
+        Self { radiance }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn bsdf(&self, _hit_record: &HitRecord, _context: &PathContext) -> Option<Bsdf> {
+        None
+    }
 
-        let cannot_refract = refraction_ratio * sin_theta > 1.0;
-        let direction = if cannot_refract || Self::reflectance(cos_theta, refraction_ratio) > random_float() {
-            vec3::reflect(unit_direction, hit_record.normal)
+    fn emitted(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.radiance.value(&TextureContext::new(u, v, *p))
+    }
+}
+
+/// Blends two materials: each hit picks `b` with probability `weight`, read through
+/// `Texture::scalar`, and `a` otherwise, which averages to the weighted mix of their scattering.
+/// Paths only track a medium when both materials bound the same one.
+pub struct Mix {
+    a: Arc<dyn Material>,
+    b: Arc<dyn Material>,
+    weight: Arc<dyn Texture>,
+}
+
+impl Mix {
+    pub fn new(a: Arc<dyn Material>, b: Arc<dyn Material>, weight: f64) -> Self {
+        Self::from_texture(a, b, Arc::new(SolidColor::gray(weight)))
+    }
+
+    pub fn from_texture(a: Arc<dyn Material>, b: Arc<dyn Material>, weight: Arc<dyn Texture>) -> Self {
+        Self { a, b, weight }
+    }
+}
+
+impl Material for Mix {
+    fn bsdf(&self, hit_record: &HitRecord, context: &PathContext) -> Option<Bsdf> {
+        let weight = self.weight.scalar(&TextureContext::from_hit(hit_record));
+        if random_float() < weight {
+            self.b.bsdf(hit_record, context)
         } else {
-            vec3::refract(unit_direction, hit_record.normal, refraction_ratio)
+            self.a.bsdf(hit_record, context)
+        }
+    }
+
+    fn interior(&self) -> Option<Medium> {
+        self.a.interior().filter(|medium| self.b.interior() == Some(*medium))
+    }
+
+    fn emitted(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let weight = self.weight.scalar(&TextureContext::new(u, v, *p));
+        (1.0 - weight) * self.a.emitted(u, v, p) + weight * self.b.emitted(u, v, p)
+    }
+}
+
+/// Wraps any material and bends its shading normal with a tangent-space normal map: the texture's
+/// (r, g, b) in [0, 1] encode the normal along (dpdu, dpdv, outward normal), green pointing up in v.
+/// Maps should be loaded with `ImageTexture::load_linear`.
+pub struct NormalMapped {
+    base: Arc<dyn Material>,
+    normal_map: Arc<dyn Texture>,
+}
+
+impl NormalMapped {
+    pub fn new(base: Arc<dyn Material>, normal_map: Arc<dyn Texture>) -> Self {
+        Self { base, normal_map }
+    }
+}
+
+impl Material for NormalMapped {
+    fn bsdf(&self, hit_record: &HitRecord, context: &PathContext) -> Option<Bsdf> {
+        let frame = Frame::from_z_and_tangent(hit_record.outward_shading_normal(), hit_record.dpdu);
+        let encoded = self.normal_map.value(&TextureContext::from_hit(hit_record));
+        let local = 2.0 * encoded - Color::with_values(1.0, 1.0, 1.0);
+
+        let mut perturbed = hit_record.clone();
+        perturbed.set_shading_normal(frame.to_world(local));
+        self.base.bsdf(&perturbed, context)
+    }
+
+    fn interior(&self) -> Option<Medium> {
+        self.base.interior()
+    }
+
+    fn emitted(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.base.emitted(u, v, p)
+    }
+}
+
+/// Wraps any material and bends its shading normal as if the surface were displaced along its normal
+/// by `scale` times the bump texture's gray value (pbrt, section 9.3).
+pub struct BumpMapped {
+    base: Arc<dyn Material>,
+    bump: Arc<dyn Texture>,
+    scale: Arc<dyn Texture>,
+}
+
+impl BumpMapped {
+    pub fn new(base: Arc<dyn Material>, bump: Arc<dyn Texture>, scale: f64) -> Self {
+        Self::from_textures(base, bump, Arc::new(SolidColor::gray(scale)))
+    }
+
+    /// Bumps whose scale varies over the surface too, read through `Texture::scalar`.
+    pub fn from_textures(base: Arc<dyn Material>, bump: Arc<dyn Texture>, scale: Arc<dyn Texture>) -> Self {
+        Self { base, bump, scale }
+    }
+
+    fn displacement(&self, ctx: &TextureContext) -> f64 {
+        self.scale.scalar(ctx) * self.bump.scalar(ctx)
+    }
+}
+
+impl Material for BumpMapped {
+    fn bsdf(&self, hit_record: &HitRecord, context: &PathContext) -> Option<Bsdf> {
+        let ctx = TextureContext::from_hit(hit_record);
+        let footprint = &hit_record.footprint;
+
+        // Finite differences over about half the pixel footprint, or a small fixed step without one.
+        let mut du = 0.5 * (footprint.dudx.abs() + footprint.dudy.abs());
+        if du == 0.0 {
+            du = 0.0005;
+        }
+        let mut dv = 0.5 * (footprint.dvdx.abs() + footprint.dvdy.abs());
+        if dv == 0.0 {
+            dv = 0.0005;
+        }
+
+        let shifted_u = TextureContext { p: ctx.p + du * hit_record.dpdu, u: ctx.u + du, ..ctx };
+        let shifted_v = TextureContext { p: ctx.p + dv * hit_record.dpdv, v: ctx.v + dv, ..ctx };
+        let displace = self.displacement(&ctx);
+        let u_displace = self.displacement(&shifted_u);
+        let v_displace = self.displacement(&shifted_v);
+
+        let n = hit_record.outward_shading_normal();
+        let (dndu, dndv) = if hit_record.front_face {
+            (hit_record.dndu, hit_record.dndv)
+        } else {
+            (-hit_record.dndu, -hit_record.dndv)
         };
+        let dpdu = hit_record.dpdu + ((u_displace - displace) / du) * n + displace * dndu;
+        let dpdv = hit_record.dpdv + ((v_displace - displace) / dv) * n + displace * dndv;
+
+        let mut bumped = vec3::cross(&dpdu, &dpdv);
+        if vec3::dot(&bumped, &n) < 0.0 {
+            bumped = -bumped;
+        }
+
+        let mut perturbed = hit_record.clone();
+        perturbed.set_shading_normal(bumped);
+        self.base.bsdf(&perturbed, context)
+    }
+
+    fn interior(&self) -> Option<Medium> {
+        self.base.interior()
+    }
+
+    fn emitted(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.base.emitted(u, v, p)
+    }
+}
+
+/// Wraps any material under a smooth or rough dielectric coat, like the clearcoat of car paint or
+/// varnish on wood, with light bouncing between the two (see `LayeredBxdf`). `absorption` is the
+/// coat's optical depth at normal incidence per channel, which tints what the coat lets through;
+/// black keeps it clear. The coat shares the base's shading normal, bumps and all.
+pub struct Coated {
+    base: Arc<dyn Material>,
+    index_of_refraction: f64,
+    roughness: Arc<dyn Texture>,
+    absorption: Color,
+}
+
+impl Coated {
+    pub fn new(base: Arc<dyn Material>, index_of_refraction: f64, roughness: f64) -> Self {
+        Self::from_texture(base, index_of_refraction, Arc::new(SolidColor::gray(roughness)), Color::new())
+    }
+
+    /// Coat with spatially varying roughness, read through `Texture::scalar`, and absorption.
+    pub fn from_texture(base: Arc<dyn Material>, index_of_refraction: f64, roughness: Arc<dyn Texture>, absorption: Color) -> Self {
+        Self { base, index_of_refraction, roughness, absorption }
+    }
+}
+
+impl Material for Coated {
+    fn bsdf(&self, hit_record: &HitRecord, context: &PathContext) -> Option<Bsdf> {
+        // The base sits under the coat, not under whatever is outside.
+        let under_coat = PathContext { exterior_ior: self.index_of_refraction, ..*context };
+        let base = self.base.bsdf(hit_record, &under_coat)?;
+
+        let alpha = TrowbridgeReitz::roughness_to_alpha(self.roughness.scalar(&TextureContext::from_hit(hit_record)));
+        let coat = RoughDielectricBxdf::new(self.index_of_refraction / context.exterior_ior, TrowbridgeReitz::new(alpha, alpha));
+        Some(base.map_bxdf(|bottom| LayeredBxdf::new(coat, bottom, self.absorption)))
+    }
+
+    fn interior(&self) -> Option<Medium> {
+        self.base.interior()
+    }
 
-        Some(ScatterRecord {
-            attenuation: Color::with_values(1.0, 1.0, 1.0),
-            scattered: Ray::new(hit_record.p, direction),
-        })
+    fn emitted(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.base.emitted(u, v, p)
     }
 }
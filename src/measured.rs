@@ -0,0 +1,265 @@
+use std::{f64::consts::PI, fs, io, path::Path, sync::Arc};
+
+use crate::{
+    bsdf::{same_hemisphere, Bsdf, BsdfFlags, BsdfSample, Bxdf},
+    color::Color,
+    hittable::HitRecord,
+    material::{Material, PathContext},
+    sampling::sample_cosine_hemisphere,
+    vec3::{dot, unit_vector, Vec3},
+};
+
+const THETA_H_RES: usize = 90;
+const THETA_D_RES: usize = 90;
+/// φ_d only covers [0, π): reciprocity makes the rest a copy.
+const PHI_D_RES: usize = 180;
+const SAMPLES: usize = THETA_H_RES * THETA_D_RES * PHI_D_RES;
+
+/// The factors MERL stores each channel's reflectance scaled up by.
+const SCALE: [f64; 3] = [1.0 / 1500.0, 1.15 / 1500.0, 1.66 / 1500.0];
+
+/// Fraction of samples drawn from the cosine-weighted hemisphere rather than the tabulated
+/// half-vector distribution, which keeps diffuse-looking materials and grazing angles covered.
+const COSINE_FRACTION: f64 = 0.3;
+
+/// Rows of θ_d, from 0, that the half vector distribution is fitted to.
+const FIT_THETA_D_ROWS: usize = 5;
+
+/// An isotropic BRDF measured by the MERL gonioreflectometer (Matusik et al. 2003), looked up through
+/// Rusinkiewicz's half/difference angles with trilinear interpolation between the measurements.
+/// Sampling mixes cosine sampling with half vectors drawn from how the data falls off in θ_h.
+pub struct Measured {
+    brdf: Arc<MerlBrdf>,
+}
+
+impl Measured {
+    /// Loads a MERL `.binary` file: three dimensions as 32-bit integers, then the red, green and
+    /// blue tables as little-endian doubles.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        if bytes.len() < 12 {
+            return Err(invalid("truncated MERL header"));
+        }
+        let dimension = |i: usize| i32::from_le_bytes(bytes[4 * i..4 * i + 4].try_into().unwrap());
+        if (dimension(0), dimension(1), dimension(2)) != (THETA_H_RES as i32, THETA_D_RES as i32, PHI_D_RES as i32) {
+            return Err(invalid("unexpected MERL table dimensions"));
+        }
+        if bytes.len() != 12 + 3 * SAMPLES * 8 {
+            return Err(invalid("MERL table size doesn't match its dimensions"));
+        }
+
+        let value = |i: usize| f64::from_le_bytes(bytes[12 + 8 * i..20 + 8 * i].try_into().unwrap());
+        let mut data = vec![0.0; 3 * SAMPLES];
+        for (channel, scale) in SCALE.iter().enumerate() {
+            for i in 0..SAMPLES {
+                // Negative values mark directions the gonioreflectometer couldn't reach.
+                data[3 * i + channel] = (value(channel * SAMPLES + i) * scale).max(0.0) as f32;
+            }
+        }
+
+        Ok(Self { brdf: Arc::new(MerlBrdf::new(data)) })
+    }
+}
+
+impl Material for Measured {
+    fn bsdf(&self, hit_record: &HitRecord, _context: &PathContext) -> Option<Bsdf> {
+        Some(Bsdf::new(hit_record, MeasuredBxdf { brdf: self.brdf.clone() }))
+    }
+}
+
+struct MerlBrdf {
+    /// Interleaved RGB, indexed by φ_d fastest, then θ_d, then θ_h.
+    data: Vec<f32>,
+    /// Probability of each θ_h bin for the sampled half vector, and its running sum.
+    theta_h_pdf: Vec<f64>,
+    theta_h_cdf: Vec<f64>,
+}
+
+impl MerlBrdf {
+    fn new(data: Vec<f32>) -> Self {
+        let mut brdf = Self { data, theta_h_pdf: Vec::new(), theta_h_cdf: Vec::new() };
+
+        // Tabulate a microfacet-like distribution of half vectors from the measurements near θ_d = 0,
+        // where f ≈ D(θ_h) F G / (4 cos² θ_h): undo the cosines, weight by cos θ_h as D is, and by
+        // the solid angle of the row.
+        let mut pdf: Vec<f64> = (0..THETA_H_RES)
+            .map(|i| {
+                let start = 3 * i * THETA_D_RES * PHI_D_RES;
+                let near = &brdf.data[start..start + 3 * FIT_THETA_D_ROWS * PHI_D_RES];
+                let mean = near.iter().map(|&v| v as f64).sum::<f64>() / near.len() as f64;
+                let (lower, upper) = (theta_h_edge(i), theta_h_edge(i + 1));
+                let cos_theta_h = (0.5 * (lower + upper)).cos();
+                let solid_angle = 2.0 * PI * (lower.cos() - upper.cos());
+                mean * cos_theta_h.powi(3) * solid_angle
+            })
+            .collect();
+        let total: f64 = pdf.iter().sum();
+        if total > 0.0 {
+            pdf.iter_mut().for_each(|p| *p /= total);
+        } else {
+            pdf = (0..THETA_H_RES).map(|i| {
+                let (lower, upper) = (theta_h_edge(i), theta_h_edge(i + 1));
+                lower.cos() - upper.cos()
+            }).collect();
+        }
+
+        brdf.theta_h_cdf = pdf
+            .iter()
+            .scan(0.0, |sum, p| {
+                *sum += p;
+                Some(*sum)
+            })
+            .collect();
+        brdf.theta_h_pdf = pdf;
+        brdf
+    }
+
+    /// The BRDF for a pair of directions in the upper hemisphere.
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Color {
+        let (theta_h, theta_d, phi_d) = half_diff(wo, wi);
+
+        // Measurement i lies at θ_h = (i / 90)² π/2, θ_d = i / 90 π/2 and φ_d = i / 180 π.
+        let h = (theta_h / (PI / 2.0)).clamp(0.0, 1.0).sqrt() * THETA_H_RES as f64;
+        let d = theta_d / (PI / 2.0) * THETA_D_RES as f64;
+        let p = phi_d / PI * PHI_D_RES as f64;
+
+        // Trilinear interpolation, clamping the θ axes and wrapping φ_d.
+        let split = |x: f64| (x.floor(), x - x.floor());
+        let ((h0, fh), (d0, fd), (p0, fp)) = (split(h), split(d), split(p));
+        let clamp = |i: f64, res: usize| (i.max(0.0) as usize).min(res - 1);
+        let wrap = |i: f64| i.rem_euclid(PHI_D_RES as f64) as usize;
+
+        let mut color = Color::new();
+        for (dh, wh) in [(0.0, 1.0 - fh), (1.0, fh)] {
+            for (dd, wd) in [(0.0, 1.0 - fd), (1.0, fd)] {
+                for (dp, wp) in [(0.0, 1.0 - fp), (1.0, fp)] {
+                    let weight = wh * wd * wp;
+                    if weight == 0.0 {
+                        continue;
+                    }
+                    let index = wrap(p0 + dp)
+                        + PHI_D_RES * clamp(d0 + dd, THETA_D_RES)
+                        + PHI_D_RES * THETA_D_RES * clamp(h0 + dh, THETA_H_RES);
+                    let texel = &self.data[3 * index..3 * index + 3];
+                    color += weight * Color::with_values(texel[0] as f64, texel[1] as f64, texel[2] as f64);
+                }
+            }
+        }
+        color
+    }
+
+    /// Density of the tabulated half vector distribution, per solid angle of the half vector.
+    fn half_vector_pdf(&self, wh: Vec3) -> f64 {
+        let theta_h = wh.z().clamp(-1.0, 1.0).acos();
+        let i = (((theta_h / (PI / 2.0)).sqrt() * THETA_H_RES as f64) as usize).min(THETA_H_RES - 1);
+        let (lower, upper) = (theta_h_edge(i), theta_h_edge(i + 1));
+        self.theta_h_pdf[i] / (2.0 * PI * (lower.cos() - upper.cos()))
+    }
+
+    fn sample_half_vector(&self, u: [f64; 3]) -> Vec3 {
+        let i = self.theta_h_cdf.partition_point(|&c| c <= u[0]).min(THETA_H_RES - 1);
+        // Uniform in solid angle within the row.
+        let (cos_lower, cos_upper) = (theta_h_edge(i).cos(), theta_h_edge(i + 1).cos());
+        let cos_theta = cos_lower + u[1] * (cos_upper - cos_lower);
+        let sin_theta = f64::sqrt(f64::max(0.0, 1.0 - cos_theta * cos_theta));
+        let phi = 2.0 * PI * u[2];
+        Vec3::with_values(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+    }
+}
+
+/// Lower edge of θ_h row `i`: rows are spaced evenly in sqrt(θ_h), to resolve highlights.
+fn theta_h_edge(i: usize) -> f64 {
+    let t = i as f64 / THETA_H_RES as f64;
+    t * t * PI / 2.0
+}
+
+/// Rusinkiewicz's half/difference angles of a pair of upper hemisphere directions: θ_h of the half
+/// vector, and θ_d, φ_d of `wi` in a frame where the half vector is the pole. φ_d is folded into
+/// [0, π), which reciprocity leaves the BRDF unchanged by.
+fn half_diff(wo: Vec3, wi: Vec3) -> (f64, f64, f64) {
+    let half = unit_vector(&(wo + wi));
+    let theta_h = half.z().clamp(-1.0, 1.0).acos();
+    let phi_h = half.y().atan2(half.x());
+
+    // Rotate wi by -φ_h about z, then by -θ_h about y.
+    let (sin_phi, cos_phi) = phi_h.sin_cos();
+    let (x, y, z) = (wi.x() * cos_phi + wi.y() * sin_phi, wi.y() * cos_phi - wi.x() * sin_phi, wi.z());
+    let (sin_theta, cos_theta) = theta_h.sin_cos();
+    let diff = Vec3::with_values(x * cos_theta - z * sin_theta, y, x * sin_theta + z * cos_theta);
+
+    let theta_d = diff.z().clamp(-1.0, 1.0).acos();
+    let mut phi_d = diff.y().atan2(diff.x());
+    if phi_d < 0.0 {
+        phi_d += PI;
+    }
+    (theta_h, theta_d, phi_d)
+}
+
+struct MeasuredBxdf {
+    brdf: Arc<MerlBrdf>,
+}
+
+impl MeasuredBxdf {
+    /// Both sides of the surface reflect like the measured top side.
+    fn upper(wo: Vec3, wi: Vec3) -> (Vec3, Vec3) {
+        if wo.z() < 0.0 { (-wo, -wi) } else { (wo, wi) }
+    }
+}
+
+impl Bxdf for MeasuredBxdf {
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Color {
+        if !same_hemisphere(wo, wi) {
+            return Color::new();
+        }
+        let (wo, wi) = Self::upper(wo, wi);
+        self.brdf.eval(wo, wi)
+    }
+
+    fn sample(&self, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        let flip = wo.z() < 0.0;
+        let (wo_up, _) = Self::upper(wo, wo);
+
+        let wi_up = if u[0] < COSINE_FRACTION {
+            sample_cosine_hemisphere((u[1], u[2]))
+        } else {
+            let remapped = (u[0] - COSINE_FRACTION) / (1.0 - COSINE_FRACTION);
+            let wh = self.brdf.sample_half_vector([remapped, u[1], u[2]]);
+            // Reflect wo about the half vector.
+            2.0 * dot(&wo_up, &wh) * wh - wo_up
+        };
+        if wi_up.z() <= 0.0 {
+            return None;
+        }
+
+        let wi = if flip { -wi_up } else { wi_up };
+        Some(BsdfSample {
+            f: self.eval(wo, wi),
+            wi,
+            pdf: self.pdf(wo, wi),
+            flags: BsdfFlags::REFLECTION | BsdfFlags::GLOSSY,
+            eta: 1.0,
+        })
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> f64 {
+        if !same_hemisphere(wo, wi) {
+            return 0.0;
+        }
+        let (wo, wi) = Self::upper(wo, wi);
+
+        let wh = wo + wi;
+        let half_vector = if wh.length_squared() > 0.0 {
+            let wh = unit_vector(&wh);
+            self.brdf.half_vector_pdf(wh) / (4.0 * dot(&wo, &wh).abs())
+        } else {
+            0.0
+        };
+        COSINE_FRACTION * wi.z() / PI + (1.0 - COSINE_FRACTION) * half_vector
+    }
+
+    fn flags(&self) -> BsdfFlags {
+        BsdfFlags::REFLECTION | BsdfFlags::GLOSSY
+    }
+}
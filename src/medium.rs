@@ -0,0 +1,195 @@
+use crate::color::Color;
+
+/// An index of refraction, either fixed or varying with wavelength. Dispersion formulas take the
+/// wavelength in micrometers, as their published coefficients do.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Ior {
+    Constant(f64),
+    /// n(λ) = a + b / λ².
+    Cauchy { a: f64, b: f64 },
+    /// n²(λ) = 1 + Σ bᵢ λ² / (λ² - cᵢ).
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Ior {
+    /// Schott N-BK7, the common crown glass.
+    pub fn bk7() -> Self {
+        Self::Sellmeier {
+            b: [1.03961212, 0.231792344, 1.01046945],
+            c: [0.00600069867, 0.0200179144, 103.560653],
+        }
+    }
+
+    /// Schott SF11, a dense flint glass with strong dispersion.
+    pub fn dense_flint() -> Self {
+        Self::Sellmeier {
+            b: [1.73759695, 0.313747346, 1.89878101],
+            c: [0.013188707, 0.0623068142, 155.23629],
+        }
+    }
+
+    pub fn fused_silica() -> Self {
+        Self::Sellmeier {
+            b: [0.6961663, 0.4079426, 0.8974794],
+            c: [0.004679148, 0.013512063, 97.93400254],
+        }
+    }
+
+    pub fn diamond() -> Self {
+        Self::Sellmeier { b: [0.3306, 4.3356, 0.0], c: [0.030625, 0.011236, 0.0] }
+    }
+
+    /// The index at `lambda` nanometers.
+    pub fn at(&self, lambda: f64) -> f64 {
+        let micrometers = lambda / 1000.0;
+        let l2 = micrometers * micrometers;
+        match *self {
+            Self::Constant(n) => n,
+            Self::Cauchy { a, b } => a + b / l2,
+            Self::Sellmeier { b, c } => f64::sqrt(1.0 + (0..3).map(|i| b[i] * l2 / (l2 - c[i])).sum::<f64>()),
+        }
+    }
+
+    pub fn is_dispersive(&self) -> bool {
+        !matches!(self, Self::Constant(_))
+    }
+}
+
+impl From<f64> for Ior {
+    fn from(n: f64) -> Self {
+        Self::Constant(n)
+    }
+}
+
+/// The homogeneous medium a dielectric encloses: its index of refraction, how strongly it absorbs
+/// and scatters each channel per unit of distance, and a priority that decides which medium fills
+/// the space where two dielectrics overlap (Schmidt and Budge 2002). Higher priorities win.
+/// Scattering is isotropic.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Medium {
+    pub index_of_refraction: Ior,
+    pub absorption: Color,
+    pub scattering: Color,
+    pub priority: u32,
+}
+
+impl Medium {
+    pub fn new(index_of_refraction: impl Into<Ior>, absorption: Color, priority: u32) -> Self {
+        Self { index_of_refraction: index_of_refraction.into(), absorption, scattering: Color::new(), priority }
+    }
+
+    /// A scattering medium with the lowest priority, from its single-scattering `albedo` and the
+    /// mean distance light travels between interactions, both per channel.
+    pub fn subsurface(index_of_refraction: impl Into<Ior>, albedo: Color, mean_free_path: Color) -> Self {
+        let extinction = Color::with_values(1.0 / mean_free_path.x(), 1.0 / mean_free_path.y(), 1.0 / mean_free_path.z());
+        let scattering = albedo * extinction;
+        Self { scattering, ..Self::new(index_of_refraction, extinction - scattering, 0) }
+    }
+
+    /// A non-absorbing medium with the lowest priority.
+    pub fn clear(index_of_refraction: impl Into<Ior>) -> Self {
+        Self::new(index_of_refraction, Color::new(), 0)
+    }
+
+    /// Fraction of light surviving `distance` through the medium (Beer-Lambert).
+    pub fn transmittance(&self, distance: f64) -> Color {
+        Color::with_values(
+            f64::exp(-self.absorption.x() * distance),
+            f64::exp(-self.absorption.y() * distance),
+            f64::exp(-self.absorption.z() * distance),
+        )
+    }
+}
+
+/// The random walk a path takes through scattering media. Every step samples distances from the
+/// extinction of one channel picked for the whole path, and weighs them against the pdfs the other
+/// channels would have had over the whole walk (balance heuristic), which keeps media whose channels
+/// scatter at different rates from piling up wild weights over hundreds of steps.
+pub struct RandomWalk {
+    channel: usize,
+    /// Per-channel pdf of the walk so far, normalized to a mean of 1.
+    pdf: Color,
+}
+
+impl RandomWalk {
+    pub fn new(u: f64) -> Self {
+        Self { channel: ((u * 3.0) as usize).min(2), pdf: Color::with_values(1.0, 1.0, 1.0) }
+    }
+
+    /// Where the path first scatters crossing `distance` of `medium`, if it does before the end,
+    /// and the factor its throughput picks up getting there. Media that only absorb never scatter
+    /// and weigh the whole segment by their transmittance.
+    pub fn step(&mut self, medium: &Medium, distance: f64, u: f64) -> (Option<f64>, Color) {
+        if medium.scattering == Color::new() {
+            return (None, medium.transmittance(distance));
+        }
+
+        let extinction = medium.absorption + medium.scattering;
+        let t = if extinction[self.channel] > 0.0 { -f64::ln(1.0 - u) / extinction[self.channel] } else { f64::INFINITY };
+        let along = t.min(distance);
+        let transmittance = Color::with_values(
+            f64::exp(-extinction.x() * along),
+            f64::exp(-extinction.y() * along),
+            f64::exp(-extinction.z() * along),
+        );
+        let (scattered, f, pdf) = if t < distance {
+            (Some(t), medium.scattering * transmittance, extinction * transmittance)
+        } else {
+            (None, transmittance, transmittance)
+        };
+
+        let pdf = self.pdf * pdf;
+        let mean = (pdf.x() + pdf.y() + pdf.z()) / 3.0;
+        if mean <= 0.0 {
+            return (None, Color::new());
+        }
+        self.pdf = pdf / mean;
+        (scattered, f / mean)
+    }
+}
+
+/// The media a path is currently inside, in the order it entered them. Empty means air.
+#[derive(Default)]
+pub struct MediumStack {
+    entered: Vec<Medium>,
+}
+
+impl MediumStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The medium the path is travelling through: the highest priority one, the latest on ties.
+    pub fn current(&self) -> Option<Medium> {
+        Self::highest(self.entered.iter())
+    }
+
+    /// The medium on the far side of a surface bounding `interior` from the inside: the highest
+    /// priority one the path is in, not counting `interior` itself when it is leaving it.
+    pub fn exterior(&self, interior: &Medium, entering: bool) -> Option<Medium> {
+        let skip = if entering { None } else { self.entered.iter().rposition(|medium| medium == interior) };
+        Self::highest(self.entered.iter().enumerate().filter(|&(i, _)| Some(i) != skip).map(|(_, medium)| medium))
+    }
+
+    /// Whether a surface bounding `interior` is a real interface. It isn't when a higher priority
+    /// medium fills that space, and the path should carry on as if the surface weren't there.
+    pub fn is_true_interface(&self, interior: &Medium, entering: bool) -> bool {
+        self.exterior(interior, entering).is_none_or(|exterior| exterior.priority <= interior.priority)
+    }
+
+    /// Records the path entering or leaving `interior`.
+    pub fn cross(&mut self, interior: Medium, entering: bool) {
+        if entering {
+            self.entered.push(interior);
+        } else if let Some(i) = self.entered.iter().rposition(|medium| *medium == interior) {
+            self.entered.remove(i);
+        }
+    }
+
+    fn highest<'a>(media: impl Iterator<Item = &'a Medium>) -> Option<Medium> {
+        media.fold(None, |best: Option<Medium>, medium| match best {
+            Some(best) if best.priority > medium.priority => Some(best),
+            _ => Some(*medium),
+        })
+    }
+}
@@ -0,0 +1,110 @@
+use std::f64::consts::PI;
+
+use crate::{sampling::sample_uniform_disk_polar, vec3::{cross, dot, unit_vector, Vec3}};
+
+/// Trowbridge-Reitz (GGX) distribution of microfacet normals in the local shading frame, with
+/// separate widths along x (dpdu) and y for anisotropic surfaces (pbrt, section 9.6).
+#[derive(Clone, Copy)]
+pub struct TrowbridgeReitz {
+    alpha_x: f64,
+    alpha_y: f64,
+}
+
+impl TrowbridgeReitz {
+    pub fn new(alpha_x: f64, alpha_y: f64) -> Self {
+        let distribution = Self { alpha_x, alpha_y };
+        if distribution.effectively_smooth() {
+            return distribution;
+        }
+        // A rough lobe that is smooth along one axis would divide by that alpha in `d`.
+        Self { alpha_x: alpha_x.max(1e-4), alpha_y: alpha_y.max(1e-4) }
+    }
+
+    /// Maps a perceptually linear roughness in [0, 1] to the distribution's alpha.
+    pub fn roughness_to_alpha(roughness: f64) -> f64 {
+        let roughness = roughness.clamp(0.0, 1.0);
+        roughness * roughness
+    }
+
+    /// Below this the lobe is narrower than anything sampling could resolve, so callers should
+    /// treat the surface as a perfect specular one.
+    pub fn effectively_smooth(&self) -> bool {
+        f64::max(self.alpha_x, self.alpha_y) < 1e-3
+    }
+
+    /// Density of microfacet normals `wm`.
+    pub fn d(&self, wm: Vec3) -> f64 {
+        let cos2_theta = wm.z() * wm.z();
+        let sin2_theta = f64::max(0.0, 1.0 - cos2_theta);
+        let tan2_theta = sin2_theta / cos2_theta;
+        if !tan2_theta.is_finite() {
+            return 0.0;
+        }
+
+        let cos4_theta = cos2_theta * cos2_theta;
+        let (cos_phi, sin_phi) = cos_sin_phi(wm);
+        let e = tan2_theta * ((cos_phi / self.alpha_x).powi(2) + (sin_phi / self.alpha_y).powi(2));
+        1.0 / (PI * self.alpha_x * self.alpha_y * cos4_theta * (1.0 + e) * (1.0 + e))
+    }
+
+    fn lambda(&self, w: Vec3) -> f64 {
+        let cos2_theta = w.z() * w.z();
+        let tan2_theta = f64::max(0.0, 1.0 - cos2_theta) / cos2_theta;
+        if !tan2_theta.is_finite() {
+            return 0.0;
+        }
+
+        let (cos_phi, sin_phi) = cos_sin_phi(w);
+        let alpha2 = (cos_phi * self.alpha_x).powi(2) + (sin_phi * self.alpha_y).powi(2);
+        (f64::sqrt(1.0 + alpha2 * tan2_theta) - 1.0) / 2.0
+    }
+
+    /// Fraction of microfacets visible from `w`.
+    pub fn g1(&self, w: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Fraction of microfacets visible from both `wo` and `wi` (height-correlated Smith).
+    pub fn g(&self, wo: Vec3, wi: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Density of microfacet normals visible from `w`; also the pdf of `sample_wm`.
+    pub fn d_visible(&self, w: Vec3, wm: Vec3) -> f64 {
+        self.g1(w) / w.z().abs() * self.d(wm) * dot(&w, &wm).abs()
+    }
+
+    /// Samples a microfacet normal visible from `w` (Heitz 2018), always in the upper hemisphere.
+    pub fn sample_wm(&self, w: Vec3, u: (f64, f64)) -> Vec3 {
+        // Stretch into the configuration where the distribution is the unit hemisphere.
+        let mut wh = unit_vector(&Vec3::with_values(self.alpha_x * w.x(), self.alpha_y * w.y(), w.z()));
+        if wh.z() < 0.0 {
+            wh = -wh;
+        }
+
+        let t1 = if wh.z() < 0.99999 {
+            unit_vector(&cross(&Vec3::with_values(0.0, 0.0, 1.0), &wh))
+        } else {
+            Vec3::with_values(1.0, 0.0, 0.0)
+        };
+        let t2 = cross(&wh, &t1);
+
+        // Uniform disk point, squashed onto the part of the hemisphere projection visible from wh.
+        let (px, py) = sample_uniform_disk_polar(u);
+        let h = f64::sqrt(1.0 - px * px);
+        let s = (1.0 + wh.z()) / 2.0;
+        let py = (1.0 - s) * h + s * py;
+        let pz = f64::sqrt(f64::max(0.0, 1.0 - px * px - py * py));
+        let nh = px * t1 + py * t2 + pz * wh;
+
+        unit_vector(&Vec3::with_values(self.alpha_x * nh.x(), self.alpha_y * nh.y(), f64::max(1e-6, nh.z())))
+    }
+}
+
+fn cos_sin_phi(w: Vec3) -> (f64, f64) {
+    let sin_theta = f64::sqrt(f64::max(0.0, 1.0 - w.z() * w.z()));
+    if sin_theta == 0.0 {
+        return (1.0, 0.0);
+    }
+    ((w.x() / sin_theta).clamp(-1.0, 1.0), (w.y() / sin_theta).clamp(-1.0, 1.0))
+}
@@ -0,0 +1,261 @@
+use std::{mem::size_of, path::Path, sync::LazyLock};
+
+use image::ImageResult;
+
+use crate::color::{linear_to_srgb, srgb_to_linear, Color};
+
+/// How texel coordinates outside of the image are brought back inside it.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WrapMode {
+    Repeat,
+    Clamp,
+}
+
+impl WrapMode {
+    fn apply(self, i: i64, size: u32) -> u32 {
+        let size = size as i64;
+        let wrapped = match self {
+            WrapMode::Repeat => i.rem_euclid(size),
+            WrapMode::Clamp => i.clamp(0, size - 1),
+        };
+        wrapped as u32
+    }
+}
+
+/// How a lookup turns the (u, v) footprint of a pixel into a color.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FilterMode {
+    /// Ignore the footprint and interpolate the four nearest texels of the full-resolution image.
+    Bilinear,
+    /// Blend bilinear lookups in the two pyramid levels whose texel size brackets the footprint width.
+    Trilinear,
+    /// Elliptically weighted average over the footprint's ellipse, for sharp results at grazing angles.
+    Ewa,
+}
+
+/// Longest-to-shortest axis ratio EWA will filter; longer ellipses are widened, trading a little blur
+/// for a bounded number of texel fetches.
+const MAX_ANISOTROPY: f64 = 8.0;
+
+/// Linear values of the 256 byte values, for sRGB-encoded and for plain data texels.
+static SRGB_DECODE: LazyLock<[f64; 256]> = LazyLock::new(|| std::array::from_fn(|byte| srgb_to_linear(byte as f64 / 255.0)));
+static UNIT_DECODE: LazyLock<[f64; 256]> = LazyLock::new(|| std::array::from_fn(|byte| byte as f64 / 255.0));
+
+/// Texels stay 8-bit as on disk and are decoded on fetch.
+struct Level {
+    width: u32,
+    height: u32,
+    srgb: bool,
+    texels: Vec<[u8; 3]>,
+}
+
+impl Level {
+    fn texel(&self, wrap: WrapMode, x: i64, y: i64) -> Color {
+        let x = wrap.apply(x, self.width);
+        let y = wrap.apply(y, self.height);
+        let [r, g, b] = self.texels[(y * self.width + x) as usize];
+        let decode: &[f64; 256] = if self.srgb { &SRGB_DECODE } else { &UNIT_DECODE };
+        Color::with_values(decode[r as usize], decode[g as usize], decode[b as usize])
+    }
+
+    fn encode(&self, linear: f64) -> u8 {
+        let value = if self.srgb { linear_to_srgb(linear) } else { linear };
+        (255.0 * value.clamp(0.0, 1.0)).round() as u8
+    }
+
+    /// Box-filtered half resolution copy; odd sizes round up and the extra row/column wraps.
+    fn downsample(&self, wrap: WrapMode) -> Level {
+        let width = u32::max(1, self.width.div_ceil(2));
+        let height = u32::max(1, self.height.div_ceil(2));
+        let mut texels = Vec::with_capacity((width * height) as usize);
+
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                let sum = self.texel(wrap, 2 * x, 2 * y)
+                    + self.texel(wrap, 2 * x + 1, 2 * y)
+                    + self.texel(wrap, 2 * x, 2 * y + 1)
+                    + self.texel(wrap, 2 * x + 1, 2 * y + 1);
+                let average = 0.25 * sum;
+                texels.push([self.encode(average.x()), self.encode(average.y()), self.encode(average.z())]);
+            }
+        }
+
+        Level { width, height, srgb: self.srgb, texels }
+    }
+}
+
+/// Image pyramid from full resolution down to a single texel, with footprint-driven filtering.
+/// Lookups take (u, v) with v pointing up, i.e. row 0 of the image is at v = 1.
+pub struct MipMap {
+    levels: Vec<Level>,
+    wrap: WrapMode,
+    filter: FilterMode,
+}
+
+impl MipMap {
+    /// Builds the pyramid over 8-bit RGB texels: with `srgb` they are sRGB-encoded color,
+    /// otherwise data in [0, 1]. Coarser levels are averaged in linear and re-encoded the same way.
+    pub fn new(width: u32, height: u32, texels: Vec<[u8; 3]>, srgb: bool, wrap: WrapMode, filter: FilterMode) -> Self {
+        let mut levels = vec![Level { width, height, srgb, texels }];
+        while let Some(last) = levels.last().filter(|level| !level.texels.is_empty() && (level.width > 1 || level.height > 1)) {
+            let next = last.downsample(wrap);
+            levels.push(next);
+        }
+
+        Self { levels, wrap, filter }
+    }
+
+    /// Reads an image file. With `srgb` the 8-bit values are color and get decoded to linear;
+    /// otherwise they are data (normal maps, masks) and are only rescaled to [0, 1].
+    pub fn load<P: AsRef<Path>>(path: P, srgb: bool, wrap: WrapMode, filter: FilterMode) -> ImageResult<Self> {
+        let image = image::open(path)?.into_rgb8();
+        let texels = image.pixels().map(|pixel| pixel.0).collect();
+
+        Ok(Self::new(image.width(), image.height(), texels, srgb, wrap, filter))
+    }
+
+    /// Reads the alpha channel of an image file as a gray mask; images without one are opaque.
+    pub fn load_alpha<P: AsRef<Path>>(path: P, wrap: WrapMode, filter: FilterMode) -> ImageResult<Self> {
+        let image = image::open(path)?.into_rgba8();
+        let texels = image.pixels().map(|pixel| [pixel[3]; 3]).collect();
+
+        Ok(Self::new(image.width(), image.height(), texels, false, wrap, filter))
+    }
+
+    /// Bytes held by the texels of all levels.
+    pub fn memory_size(&self) -> usize {
+        self.levels.iter().map(|level| level.texels.len() * size_of::<[u8; 3]>()).sum()
+    }
+
+    pub fn filter(&self, u: f64, v: f64, dudx: f64, dudy: f64, dvdx: f64, dvdy: f64) -> Color {
+        // An empty image shows up in bright cyan rather than bringing the render down.
+        if self.levels[0].texels.is_empty() {
+            return Color::with_values(0.0, 1.0, 1.0);
+        }
+
+        let (width, height) = (self.levels[0].width as f64, self.levels[0].height as f64);
+
+        match self.filter {
+            FilterMode::Bilinear => self.bilinear(0, u, v),
+            FilterMode::Trilinear => {
+                let footprint_width = 2.0 * f64::max(
+                    f64::max(dudx.abs(), dudy.abs()) * width,
+                    f64::max(dvdx.abs(), dvdy.abs()) * height,
+                );
+                self.trilinear(u, v, footprint_width)
+            }
+            FilterMode::Ewa => {
+                // Footprint axes in texels of the finest level, with y pointing down the image.
+                let mut major = (dudx * width, -dvdx * height);
+                let mut minor = (dudy * width, -dvdy * height);
+                let length = |axis: (f64, f64)| f64::sqrt(axis.0 * axis.0 + axis.1 * axis.1);
+                if length(major) < length(minor) {
+                    std::mem::swap(&mut major, &mut minor);
+                }
+
+                let major_length = length(major);
+                let mut minor_length = length(minor);
+                if minor_length * MAX_ANISOTROPY < major_length && minor_length > 0.0 {
+                    let scale = major_length / (minor_length * MAX_ANISOTROPY);
+                    minor = (minor.0 * scale, minor.1 * scale);
+                    minor_length *= scale;
+                }
+                if minor_length == 0.0 {
+                    return self.bilinear(0, u, v);
+                }
+
+                let lod = f64::max(0.0, minor_length.log2());
+                let level = lod.floor() as usize;
+                let t = lod - lod.floor();
+                (1.0 - t) * self.ewa(level, u, v, major, minor) + t * self.ewa(level + 1, u, v, major, minor)
+            }
+        }
+    }
+
+    fn trilinear(&self, u: f64, v: f64, footprint_width: f64) -> Color {
+        let lod = f64::max(footprint_width, 1e-8).log2();
+        let max_level = (self.levels.len() - 1) as f64;
+        if lod <= 0.0 {
+            return self.bilinear(0, u, v);
+        }
+        if lod >= max_level {
+            return self.bilinear(self.levels.len() - 1, u, v);
+        }
+
+        let level = lod.floor() as usize;
+        let t = lod - lod.floor();
+        (1.0 - t) * self.bilinear(level, u, v) + t * self.bilinear(level + 1, u, v)
+    }
+
+    fn bilinear(&self, level: usize, u: f64, v: f64) -> Color {
+        let level = &self.levels[level];
+
+        // Texel centers sit at half-integer coordinates.
+        let x = u * level.width as f64 - 0.5;
+        let y = (1.0 - v) * level.height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (dx, dy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        (1.0 - dx) * (1.0 - dy) * level.texel(self.wrap, x0, y0)
+            + dx * (1.0 - dy) * level.texel(self.wrap, x0 + 1, y0)
+            + (1.0 - dx) * dy * level.texel(self.wrap, x0, y0 + 1)
+            + dx * dy * level.texel(self.wrap, x0 + 1, y0 + 1)
+    }
+
+    /// Gaussian-weighted sum over the texels of `level` inside the ellipse spanned by the two axes
+    /// (given in finest-level texels), following pbrt-v3's `MIPMap::EWA`.
+    fn ewa(&self, level: usize, u: f64, v: f64, axis0: (f64, f64), axis1: (f64, f64)) -> Color {
+        if level >= self.levels.len() {
+            let coarsest = &self.levels[self.levels.len() - 1];
+            return coarsest.texel(self.wrap, 0, 0);
+        }
+
+        let texels = &self.levels[level];
+        let scale = 1.0 / (1u64 << level) as f64;
+        let s = u * texels.width as f64 - 0.5;
+        let t = (1.0 - v) * texels.height as f64 - 0.5;
+        let (ds0, dt0) = (axis0.0 * scale, axis0.1 * scale);
+        let (ds1, dt1) = (axis1.0 * scale, axis1.1 * scale);
+
+        // Implicit ellipse A s² + B s t + C t² < 1, grown by one texel so it never falls between samples.
+        let mut a = dt0 * dt0 + dt1 * dt1 + 1.0;
+        let mut b = -2.0 * (ds0 * dt0 + ds1 * dt1);
+        let mut c = ds0 * ds0 + ds1 * ds1 + 1.0;
+        let inv_f = 1.0 / (a * c - b * b * 0.25);
+        a *= inv_f;
+        b *= inv_f;
+        c *= inv_f;
+
+        let det = -b * b + 4.0 * a * c;
+        let inv_det = 1.0 / det;
+        let u_sqrt = f64::sqrt(det * c);
+        let v_sqrt = f64::sqrt(a * det);
+        let s0 = (s - 2.0 * inv_det * u_sqrt).ceil() as i64;
+        let s1 = (s + 2.0 * inv_det * u_sqrt).floor() as i64;
+        let t0 = (t - 2.0 * inv_det * v_sqrt).ceil() as i64;
+        let t1 = (t + 2.0 * inv_det * v_sqrt).floor() as i64;
+
+        const ALPHA: f64 = 2.0;
+        let mut sum = Color::new();
+        let mut sum_weights = 0.0;
+        for it in t0..=t1 {
+            let tt = it as f64 - t;
+            for is in s0..=s1 {
+                let ss = is as f64 - s;
+                let r2 = a * ss * ss + b * ss * tt + c * tt * tt;
+                if r2 < 1.0 {
+                    let weight = f64::exp(-ALPHA * r2) - f64::exp(-ALPHA);
+                    sum += weight * texels.texel(self.wrap, is, it);
+                    sum_weights += weight;
+                }
+            }
+        }
+
+        if sum_weights > 0.0 {
+            sum / sum_weights
+        } else {
+            self.bilinear(level, u, v)
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::{color::Color, ray::Point3, texture::{Texture, TextureContext}, vec3::{dot, Vec3}};
+
+const POINT_COUNT: usize = 256;
+
+/// Seeded permutation tables hashing integer lattice coordinates to `0..POINT_COUNT`.
+struct Lattice {
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Lattice {
+    fn new(rng: &mut StdRng) -> Self {
+        let mut generate_perm = || {
+            let mut perm: Vec<usize> = (0..POINT_COUNT).collect();
+            perm.shuffle(rng);
+            perm
+        };
+
+        Self {
+            perm_x: generate_perm(),
+            perm_y: generate_perm(),
+            perm_z: generate_perm(),
+        }
+    }
+
+    fn hash(&self, i: i64, j: i64, k: i64) -> usize {
+        let mask = POINT_COUNT as i64 - 1;
+        self.perm_x[(i & mask) as usize] ^ self.perm_y[(j & mask) as usize] ^ self.perm_z[(k & mask) as usize]
+    }
+}
+
+fn random_unit_vector(rng: &mut StdRng) -> Vec3 {
+    loop {
+        let p = Vec3::with_values(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+        let lensq = p.length_squared();
+        if 1e-160 < lensq && lensq <= 1.0 {
+            return p / f64::sqrt(lensq);
+        }
+    }
+}
+
+/// Gradient (Perlin) noise. Unlike everything built on `utils::random_float`, the same seed always
+/// produces the same field, so procedural materials look identical from render to render.
+pub struct Perlin {
+    lattice: Lattice,
+    gradients: Vec<Vec3>,
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let gradients = (0..POINT_COUNT).map(|_| random_unit_vector(&mut rng)).collect();
+
+        Self {
+            lattice: Lattice::new(&mut rng),
+            gradients,
+        }
+    }
+
+    /// Noise value in roughly [-1, 1], zero at every lattice point.
+    pub fn noise(&self, p: &Point3) -> f64 {
+        let (fx, fy, fz) = (p.x().floor(), p.y().floor(), p.z().floor());
+        let (u, v, w) = (p.x() - fx, p.y() - fy, p.z() - fz);
+        let (i, j, k) = (fx as i64, fy as i64, fz as i64);
+
+        // Quintic fade, so the noise has continuous second derivatives across cell boundaries.
+        let fade = |t: f64| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+        let (uu, vv, ww) = (fade(u), fade(v), fade(w));
+
+        let mut accum = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let gradient = self.gradients[self.lattice.hash(i + di, j + dj, k + dk)];
+                    let (a, b, c) = (di as f64, dj as f64, dk as f64);
+                    let weight = Vec3::with_values(u - a, v - b, w - c);
+
+                    accum += (a * uu + (1.0 - a) * (1.0 - uu))
+                        * (b * vv + (1.0 - b) * (1.0 - vv))
+                        * (c * ww + (1.0 - c) * (1.0 - ww))
+                        * dot(&gradient, &weight);
+                }
+            }
+        }
+
+        accum
+    }
+
+    /// Fractal Brownian motion: `octaves` layers of noise, each at twice the frequency and half the
+    /// amplitude of the previous one.
+    pub fn fbm(&self, p: &Point3, octaves: u32) -> f64 {
+        let mut accum = 0.0;
+        let mut temp_p = *p;
+        let mut weight = 1.0;
+
+        for _ in 0..octaves {
+            accum += weight * self.noise(&temp_p);
+            weight *= 0.5;
+            temp_p *= 2.0;
+        }
+
+        accum
+    }
+
+    /// Like `fbm`, but summing absolute values, which gives the creased look of turbulent flow.
+    pub fn turbulence(&self, p: &Point3, octaves: u32) -> f64 {
+        let mut accum = 0.0;
+        let mut temp_p = *p;
+        let mut weight = 1.0;
+
+        for _ in 0..octaves {
+            accum += weight * self.noise(&temp_p).abs();
+            weight *= 0.5;
+            temp_p *= 2.0;
+        }
+
+        accum
+    }
+}
+
+/// Worley (cellular) noise: one seeded feature point per unit cell.
+pub struct Worley {
+    lattice: Lattice,
+    feature_points: Vec<Vec3>,
+}
+
+impl Worley {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let feature_points = (0..POINT_COUNT)
+            .map(|_| Vec3::with_values(rng.gen(), rng.gen(), rng.gen()))
+            .collect();
+
+        Self {
+            lattice: Lattice::new(&mut rng),
+            feature_points,
+        }
+    }
+
+    /// Distances from `p` to the nearest and second nearest feature points (F1, F2).
+    pub fn distances(&self, p: &Point3) -> (f64, f64) {
+        let (i, j, k) = (p.x().floor() as i64, p.y().floor() as i64, p.z().floor() as i64);
+        let mut f1 = f64::INFINITY;
+        let mut f2 = f64::INFINITY;
+
+        for di in -1..=1 {
+            for dj in -1..=1 {
+                for dk in -1..=1 {
+                    let cell = Vec3::with_values((i + di) as f64, (j + dj) as f64, (k + dk) as f64);
+                    let feature = cell + self.feature_points[self.lattice.hash(i + di, j + dj, k + dk)];
+                    let distance = (feature - *p).length();
+
+                    if distance < f1 {
+                        f2 = f1;
+                        f1 = distance;
+                    } else if distance < f2 {
+                        f2 = distance;
+                    }
+                }
+            }
+        }
+
+        (f1, f2)
+    }
+}
+
+fn lerp(a: Color, b: Color, t: f64) -> Color {
+    (1.0 - t) * a + t * b
+}
+
+/// Veined marble: a sine wave along Z, phase-shifted by turbulence.
+pub struct MarbleTexture {
+    noise: Perlin,
+    scale: f64,
+    base: Color,
+    vein: Color,
+}
+
+impl MarbleTexture {
+    pub fn new(seed: u64, scale: f64, base: Color, vein: Color) -> Self {
+        Self { noise: Perlin::new(seed), scale, base, vein }
+    }
+}
+
+impl Texture for MarbleTexture {
+    fn value(&self, ctx: &TextureContext) -> Color {
+        let p = &ctx.p;
+        let t = 0.5 * (1.0 + f64::sin(self.scale * p.z() + 10.0 * self.noise.turbulence(p, 7)));
+        lerp(self.vein, self.base, t)
+    }
+}
+
+/// Wood: concentric growth rings around the Y axis, wobbled by low-frequency noise.
+pub struct WoodTexture {
+    noise: Perlin,
+    rings_per_unit: f64,
+    light: Color,
+    dark: Color,
+}
+
+impl WoodTexture {
+    pub fn new(seed: u64, rings_per_unit: f64, light: Color, dark: Color) -> Self {
+        Self { noise: Perlin::new(seed), rings_per_unit, light, dark }
+    }
+}
+
+impl Texture for WoodTexture {
+    fn value(&self, ctx: &TextureContext) -> Color {
+        let p = &ctx.p;
+        let radius = f64::sqrt(p.x() * p.x() + p.z() * p.z());
+        let grain = Vec3::with_values(p.x(), p.y() * 0.1, p.z());
+        let rings = self.rings_per_unit * radius + 2.0 * self.noise.fbm(&grain, 3);
+
+        // Sharpen the ring profile so late wood forms thin dark bands.
+        let t = f64::powf(rings - rings.floor(), 4.0);
+        lerp(self.light, self.dark, t)
+    }
+}
+
+/// Granite: Worley cells for the crystals with darker seams along cell borders, plus fine fBm speckle.
+pub struct GraniteTexture {
+    cells: Worley,
+    speckle: Perlin,
+    scale: f64,
+    crystal: Color,
+    seam: Color,
+}
+
+impl GraniteTexture {
+    pub fn new(seed: u64, scale: f64, crystal: Color, seam: Color) -> Self {
+        Self {
+            cells: Worley::new(seed),
+            speckle: Perlin::new(seed.wrapping_add(1)),
+            scale,
+            crystal,
+            seam,
+        }
+    }
+}
+
+impl Texture for GraniteTexture {
+    fn value(&self, ctx: &TextureContext) -> Color {
+        let p = &ctx.p;
+        let scaled = self.scale * *p;
+        let (f1, f2) = self.cells.distances(&scaled);
+        let border = f64::min(1.0, (f2 - f1) * 4.0);
+        let speckle = 0.5 * (1.0 + self.speckle.fbm(&(4.0 * scaled), 4));
+
+        lerp(self.seam, self.crystal, border) * (0.6 + 0.4 * speckle)
+    }
+}
+
+/// Clouds: fBm thresholded by `coverage` (0 = clear sky, 1 = overcast) and blended over the sky color.
+pub struct CloudTexture {
+    noise: Perlin,
+    scale: f64,
+    coverage: f64,
+    sky: Color,
+    cloud: Color,
+}
+
+impl CloudTexture {
+    pub fn new(seed: u64, scale: f64, coverage: f64, sky: Color, cloud: Color) -> Self {
+        Self { noise: Perlin::new(seed), scale, coverage, sky, cloud }
+    }
+}
+
+impl Texture for CloudTexture {
+    fn value(&self, ctx: &TextureContext) -> Color {
+        let p = &ctx.p;
+        let density = self.noise.fbm(&(self.scale * *p), 6) + self.coverage - 0.5;
+        let t = density.clamp(0.0, 1.0);
+        lerp(self.sky, self.cloud, t * t * (3.0 - 2.0 * t))
+    }
+}
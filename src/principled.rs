@@ -0,0 +1,263 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use crate::{
+    bsdf::{same_hemisphere, Bsdf, BsdfFlags, BsdfSample, Bxdf, MicrofacetReflectionBxdf, MixtureBxdf, RoughDielectricBxdf},
+    color::Color,
+    fresnel::{schlick_weight, Fresnel},
+    hittable::HitRecord,
+    material::{Material, PathContext},
+    microfacet::TrowbridgeReitz,
+    sampling::sample_cosine_hemisphere,
+    texture::{SolidColor, Texture, TextureContext},
+    vec3::{self, dot, unit_vector, Vec3},
+};
+
+/// Burley's principled BSDF (2012, with the transmission of the 2015 notes), with the parameters
+/// artists know from other tools. Everything is in [0, 1]; set the fields after `default()`.
+pub struct Principled {
+    pub base_color: Arc<dyn Texture>,
+    pub metallic: Arc<dyn Texture>,
+    pub roughness: Arc<dyn Texture>,
+    /// Dielectric reflectance at normal incidence, scaled so 0.5 means 4% (an IOR of 1.5).
+    pub specular: f64,
+    /// How much the dielectric specular takes on the hue of the base color.
+    pub specular_tint: f64,
+    /// Extra grazing retroreflection, for cloth.
+    pub sheen: f64,
+    /// A second, always isotropic and colorless GGX layer.
+    pub clearcoat: f64,
+    /// Sharpness of the clearcoat, from satin to gloss.
+    pub clearcoat_gloss: f64,
+    /// Fraction of the dielectric part that is glass rather than diffuse.
+    pub transmission: f64,
+    /// Blends the diffuse lobe toward Hanrahan-Krueger's flatter subsurface approximation.
+    pub subsurface: f64,
+}
+
+impl Default for Principled {
+    fn default() -> Self {
+        Self {
+            base_color: Arc::new(SolidColor::gray(0.8)),
+            metallic: Arc::new(SolidColor::gray(0.0)),
+            roughness: Arc::new(SolidColor::gray(0.5)),
+            specular: 0.5,
+            specular_tint: 0.0,
+            sheen: 0.0,
+            clearcoat: 0.0,
+            clearcoat_gloss: 1.0,
+            transmission: 0.0,
+            subsurface: 0.0,
+        }
+    }
+}
+
+impl Material for Principled {
+    fn bsdf(&self, hit_record: &HitRecord, _context: &PathContext) -> Option<Bsdf> {
+        let ctx = TextureContext::from_hit(hit_record);
+        let base_color = self.base_color.value(&ctx);
+        let metallic = self.metallic.scalar(&ctx).clamp(0.0, 1.0);
+        let roughness = self.roughness.scalar(&ctx).clamp(0.0, 1.0);
+        let alpha = TrowbridgeReitz::roughness_to_alpha(roughness);
+        let distribution = TrowbridgeReitz::new(alpha, alpha);
+
+        let white = Color::with_values(1.0, 1.0, 1.0);
+        let base_luminance = luminance(base_color);
+        let tint = if base_luminance > 0.0 { base_color / base_luminance } else { white };
+        let lerp = |t: f64, a: Color, b: Color| (1.0 - t) * a + t * b;
+
+        let dielectric_r0 = 0.08 * self.specular * lerp(self.specular_tint, white, tint);
+        let r0 = lerp(metallic, dielectric_r0, base_color);
+        let diffuse_weight = (1.0 - metallic) * (1.0 - self.transmission);
+        let transmission_weight = (1.0 - metallic) * self.transmission;
+        let specular_weight = 1.0 - transmission_weight;
+
+        // Sampling weights roughly follow each lobe's albedo, with floors so dim lobes still get
+        // sampled at grazing angles where they brighten.
+        let mut lobes = MixtureBxdf::default();
+        lobes.add(
+            diffuse_weight * white,
+            diffuse_weight * f64::max(base_luminance, 0.1),
+            DisneyDiffuseBxdf {
+                base_color,
+                roughness,
+                subsurface: self.subsurface,
+                sheen: self.sheen * lerp(0.5, white, tint),
+            },
+        );
+        lobes.add(
+            specular_weight * white,
+            specular_weight * f64::max(luminance(r0), 0.25),
+            MicrofacetReflectionBxdf::new(distribution, Fresnel::Schlick(r0)),
+        );
+        lobes.add(
+            white,
+            0.25 * self.clearcoat,
+            ClearcoatBxdf {
+                strength: self.clearcoat,
+                alpha: 0.1 + (0.001 - 0.1) * self.clearcoat_gloss,
+            },
+        );
+        lobes.add(
+            transmission_weight * base_color,
+            transmission_weight,
+            RoughDielectricBxdf::new(f64::max(specular_to_ior(self.specular), 1.01), distribution),
+        );
+
+        Some(Bsdf::new(hit_record, lobes))
+    }
+}
+
+fn luminance(c: Color) -> f64 {
+    0.2126 * c.x() + 0.7152 * c.y() + 0.0722 * c.z()
+}
+
+/// Inverts `specular = 12.5 * ((η - 1) / (η + 1))²`.
+fn specular_to_ior(specular: f64) -> f64 {
+    let root_r0 = f64::sqrt((0.08 * specular).clamp(0.0, 0.99));
+    (1.0 + root_r0) / (1.0 - root_r0)
+}
+
+/// Burley's diffuse, with its grazing retroreflection, the subsurface approximation and sheen.
+struct DisneyDiffuseBxdf {
+    base_color: Color,
+    roughness: f64,
+    subsurface: f64,
+    sheen: Color,
+}
+
+impl Bxdf for DisneyDiffuseBxdf {
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Color {
+        if !same_hemisphere(wo, wi) {
+            return Color::new();
+        }
+        let (cos_theta_o, cos_theta_i) = (wo.z().abs(), wi.z().abs());
+        let wh = wo + wi;
+        if wh.length_squared() == 0.0 || cos_theta_o == 0.0 || cos_theta_i == 0.0 {
+            return Color::new();
+        }
+        let cos_theta_d = dot(&wi, &unit_vector(&wh)).abs();
+
+        let (fl, fv) = (schlick_weight(cos_theta_i), schlick_weight(cos_theta_o));
+        let retro = |f90: f64| (1.0 + (f90 - 1.0) * fl) * (1.0 + (f90 - 1.0) * fv);
+        let fd = retro(0.5 + 2.0 * self.roughness * cos_theta_d * cos_theta_d);
+        let fss = retro(self.roughness * cos_theta_d * cos_theta_d);
+        let ss = 1.25 * (fss * (1.0 / (cos_theta_i + cos_theta_o) - 0.5) + 0.5);
+
+        let diffuse = ((1.0 - self.subsurface) * fd + self.subsurface * ss) / PI * self.base_color;
+        diffuse + schlick_weight(cos_theta_d) * self.sheen
+    }
+
+    fn sample(&self, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        let mut wi = sample_cosine_hemisphere((u[1], u[2]));
+        if wo.z() < 0.0 {
+            wi = Vec3::with_values(wi.x(), wi.y(), -wi.z());
+        }
+
+        Some(BsdfSample {
+            f: self.eval(wo, wi),
+            wi,
+            pdf: self.pdf(wo, wi),
+            flags: BsdfFlags::REFLECTION | BsdfFlags::DIFFUSE,
+            eta: 1.0,
+        })
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> f64 {
+        if !same_hemisphere(wo, wi) {
+            return 0.0;
+        }
+        wi.z().abs() / PI
+    }
+
+    fn flags(&self) -> BsdfFlags {
+        BsdfFlags::REFLECTION | BsdfFlags::DIFFUSE
+    }
+}
+
+/// The clearcoat: a GTR1 lobe with the Fresnel of an IOR 1.5 coating and a fixed Smith roughness.
+struct ClearcoatBxdf {
+    strength: f64,
+    alpha: f64,
+}
+
+impl ClearcoatBxdf {
+    /// Generalized Trowbridge-Reitz with γ = 1, whose long tail gives the coat its halo.
+    fn gtr1(&self, cos_theta_h: f64) -> f64 {
+        if self.alpha >= 1.0 {
+            return 1.0 / PI;
+        }
+        let a2 = self.alpha * self.alpha;
+        (a2 - 1.0) / (PI * a2.ln() * (1.0 + (a2 - 1.0) * cos_theta_h * cos_theta_h))
+    }
+
+    fn smith_g1(cos_theta: f64) -> f64 {
+        let a2 = 0.25 * 0.25;
+        2.0 * cos_theta / (cos_theta + f64::sqrt(a2 + (1.0 - a2) * cos_theta * cos_theta))
+    }
+
+    fn half_vector(wo: Vec3, wi: Vec3) -> Option<Vec3> {
+        let wh = wo + wi;
+        if wh.length_squared() == 0.0 {
+            return None;
+        }
+        let wh = unit_vector(&wh);
+        Some(if wh.z() < 0.0 { -wh } else { wh })
+    }
+}
+
+impl Bxdf for ClearcoatBxdf {
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Color {
+        if !same_hemisphere(wo, wi) {
+            return Color::new();
+        }
+        let (cos_theta_o, cos_theta_i) = (wo.z().abs(), wi.z().abs());
+        let Some(wh) = Self::half_vector(wo, wi) else {
+            return Color::new();
+        };
+        if cos_theta_o == 0.0 || cos_theta_i == 0.0 {
+            return Color::new();
+        }
+
+        let fresnel = 0.04 + 0.96 * schlick_weight(dot(&wo, &wh).abs());
+        let g = Self::smith_g1(cos_theta_o) * Self::smith_g1(cos_theta_i);
+        let value = 0.25 * self.strength * self.gtr1(wh.z()) * fresnel * g / (4.0 * cos_theta_o * cos_theta_i);
+        Color::with_values(value, value, value)
+    }
+
+    fn sample(&self, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        let a2 = self.alpha * self.alpha;
+        let cos_theta_h = f64::sqrt(f64::max(0.0, (1.0 - a2.powf(1.0 - u[1])) / (1.0 - a2)));
+        let sin_theta_h = f64::sqrt(f64::max(0.0, 1.0 - cos_theta_h * cos_theta_h));
+        let phi = 2.0 * PI * u[2];
+        let mut wh = Vec3::with_values(sin_theta_h * phi.cos(), sin_theta_h * phi.sin(), cos_theta_h);
+        if wo.z() < 0.0 {
+            wh = -wh;
+        }
+
+        let wi = vec3::reflect(-wo, wh);
+        if !same_hemisphere(wo, wi) {
+            return None;
+        }
+        Some(BsdfSample {
+            f: self.eval(wo, wi),
+            wi,
+            pdf: self.pdf(wo, wi),
+            flags: BsdfFlags::REFLECTION | BsdfFlags::GLOSSY,
+            eta: 1.0,
+        })
+    }
+
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> f64 {
+        if !same_hemisphere(wo, wi) {
+            return 0.0;
+        }
+        let Some(wh) = Self::half_vector(wo, wi) else {
+            return 0.0;
+        };
+        self.gtr1(wh.z()) * wh.z() / (4.0 * dot(&wo, &wh).abs())
+    }
+
+    fn flags(&self) -> BsdfFlags {
+        BsdfFlags::REFLECTION | BsdfFlags::GLOSSY
+    }
+}
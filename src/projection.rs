@@ -0,0 +1,155 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use crate::{color::Color, hittable::Footprint, ray::Point3, texture::{Texture, TextureContext}, vec3::{cross, dot, unit_vector, Vec3}};
+
+/// Coordinate system a projection works in.
+pub enum TextureSpace {
+    World,
+    /// Relative to a frame attached to an object, so the pattern stays put on the object wherever it
+    /// is placed: the origin is the projection center and the local y axis is the projection's up.
+    Object { origin: Point3, axes: [Vec3; 3] },
+}
+
+impl TextureSpace {
+    /// Object space centered at `origin` with its y axis along `up`.
+    pub fn object(origin: Point3, up: Vec3) -> Self {
+        let y = unit_vector(&up);
+        let helper = if y.x().abs() > 0.9 { Vec3::with_values(0.0, 0.0, 1.0) } else { Vec3::with_values(1.0, 0.0, 0.0) };
+        let z = unit_vector(&cross(&helper, &y));
+        let x = cross(&y, &z);
+        TextureSpace::Object { origin, axes: [x, y, z] }
+    }
+
+    fn point_to_local(&self, p: Point3) -> Point3 {
+        match self {
+            TextureSpace::World => p,
+            TextureSpace::Object { origin, axes } => Self::vector_to_local(axes, p - *origin),
+        }
+    }
+
+    fn direction_to_local(&self, v: Vec3) -> Vec3 {
+        match self {
+            TextureSpace::World => v,
+            TextureSpace::Object { axes, .. } => Self::vector_to_local(axes, v),
+        }
+    }
+
+    fn vector_to_local(axes: &[Vec3; 3], v: Vec3) -> Vec3 {
+        Vec3::with_values(dot(&v, &axes[0]), dot(&v, &axes[1]), dot(&v, &axes[2]))
+    }
+}
+
+/// How a point in texture space becomes (u, v).
+#[derive(Clone, Copy)]
+pub enum Projection {
+    /// Onto the local xy plane: (u, v) = (x, y).
+    Planar,
+    /// Around the local y axis: u is the angle, v the height.
+    Cylindrical,
+    /// Longitude and latitude around the local origin, laid out like `Sphere`'s own (u, v).
+    Spherical,
+    /// Planar projections along all three local axes, blended by how much the normal faces each one.
+    /// Higher `sharpness` narrows the blend zones.
+    Triplanar { sharpness: f64 },
+}
+
+/// Lookup point, its screen-space offsets and the outward normal, all in texture space.
+struct LocalPoint {
+    p: Point3,
+    dpdx: Vec3,
+    dpdy: Vec3,
+    normal: Vec3,
+}
+
+/// Evaluates any texture with generated coordinates instead of the surface's (u, v), for objects
+/// without a usable parameterization. The wrapped texture also receives `p` and `normal` in texture
+/// space, so 3D textures follow the same choice of world or object space.
+pub struct ProjectedTexture {
+    texture: Arc<dyn Texture>,
+    projection: Projection,
+    space: TextureSpace,
+    scale: f64,
+}
+
+impl ProjectedTexture {
+    /// `scale` is the number of texture repeats per unit of length (for planar and triplanar) or
+    /// along the axis (cylindrical).
+    pub fn new(texture: Arc<dyn Texture>, projection: Projection, space: TextureSpace, scale: f64) -> Self {
+        Self { texture, projection, space, scale }
+    }
+
+    /// Evaluates the texture with (u, v) from `map`, deriving the (u, v) footprint by pushing the
+    /// screen-space position offsets through the same mapping.
+    fn lookup(&self, ctx: &TextureContext, local: &LocalPoint, periodic_u: bool, map: impl Fn(Point3) -> (f64, f64)) -> Color {
+        let (u, v) = map(local.p);
+        let (dudx, dvdx) = Self::difference(map(local.p + local.dpdx), (u, v), periodic_u);
+        let (dudy, dvdy) = Self::difference(map(local.p + local.dpdy), (u, v), periodic_u);
+
+        self.texture.value(&TextureContext {
+            p: local.p,
+            u,
+            v,
+            normal: local.normal,
+            front_face: ctx.front_face,
+            footprint: Footprint { dpdx: local.dpdx, dpdy: local.dpdy, dudx, dudy, dvdx, dvdy },
+        })
+    }
+
+    /// `to - from`. With `periodic_u`, u is an angle in [0, 1) and its jump across the seam is
+    /// unwrapped; every other coordinate keeps its full difference, however large the footprint.
+    fn difference(to: (f64, f64), from: (f64, f64), periodic_u: bool) -> (f64, f64) {
+        let du = to.0 - from.0;
+        let du = if !periodic_u { du } else if du > 0.5 { du - 1.0 } else if du < -0.5 { du + 1.0 } else { du };
+        (du, to.1 - from.1)
+    }
+}
+
+impl Texture for ProjectedTexture {
+    fn value(&self, ctx: &TextureContext) -> Color {
+        let local = LocalPoint {
+            p: self.space.point_to_local(ctx.p),
+            dpdx: self.space.direction_to_local(ctx.footprint.dpdx),
+            dpdy: self.space.direction_to_local(ctx.footprint.dpdy),
+            normal: self.space.direction_to_local(ctx.normal),
+        };
+        let normal = local.normal;
+        let scale = self.scale;
+
+        match self.projection {
+            Projection::Planar => self.lookup(ctx, &local, false, |q| (scale * q.x(), scale * q.y())),
+            Projection::Cylindrical => self.lookup(ctx, &local, true, |q| {
+                ((f64::atan2(-q.z(), q.x()) + PI) / (2.0 * PI), scale * q.y())
+            }),
+            Projection::Spherical => self.lookup(ctx, &local, true, |q| {
+                let d = unit_vector(&q);
+                ((f64::atan2(-d.z(), d.x()) + PI) / (2.0 * PI), f64::acos((-d.y()).clamp(-1.0, 1.0)) / PI)
+            }),
+            Projection::Triplanar { sharpness } => {
+                let mut weights = [
+                    normal.x().abs().powf(sharpness),
+                    normal.y().abs().powf(sharpness),
+                    normal.z().abs().powf(sharpness),
+                ];
+                let total: f64 = weights.iter().sum();
+                if total > 0.0 {
+                    weights.iter_mut().for_each(|w| *w /= total);
+                } else {
+                    // No normal to blend by (e.g. an emission lookup): weigh the three planes equally.
+                    weights = [1.0 / 3.0; 3];
+                }
+
+                let mut color = Color::new();
+                if weights[0] > 0.0 {
+                    color += weights[0] * self.lookup(ctx, &local, false, |q| (scale * q.z(), scale * q.y()));
+                }
+                if weights[1] > 0.0 {
+                    color += weights[1] * self.lookup(ctx, &local, false, |q| (scale * q.x(), scale * q.z()));
+                }
+                if weights[2] > 0.0 {
+                    color += weights[2] * self.lookup(ctx, &local, false, |q| (scale * q.x(), scale * q.y()));
+                }
+                color
+            }
+        }
+    }
+}
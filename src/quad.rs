@@ -0,0 +1,126 @@
+use std::sync::Arc;
+
+use crate::{hittable::{HitRecord, Hittable}, hittable_list::HittableList, interval::Interval, material::Material, ray::{Point3, Ray}, utils::{gamma, random_float}, vec3::{abs, cross, dot, unit_vector, Vec3}};
+
+/// Parallelogram spanned by the edges `u` and `v` from the corner `q`.
+pub struct Quad {
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    normal: Vec3,
+    d: f64,
+    area: f64,
+    mat: Arc<dyn Material>,
+}
+
+impl Quad {
+    pub fn new(q: Point3, u: Vec3, v: Vec3, mat: Arc<dyn Material>) -> Self {
+        let n = cross(&u, &v);
+        let normal = unit_vector(&n);
+
+        Quad {
+            q,
+            u,
+            v,
+            w: n / dot(&n, &n),
+            normal,
+            d: dot(&normal, &q),
+            area: n.length(),
+            mat,
+        }
+    }
+
+    /// Ray/plane intersection followed by the inside test; returns `t` and the planar coordinates
+    /// (alpha, beta) of the hit along `u` and `v`.
+    fn intersect(&self, ray: &Ray, ray_t: Interval) -> Option<(f64, f64, f64)> {
+        let denom = dot(&self.normal, &ray.direction());
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = (self.d - dot(&self.normal, &ray.origin())) / denom;
+        if !ray_t.surrounds(t) {
+            return None;
+        }
+
+        let planar_hit = ray.at(t) - self.q;
+        let alpha = dot(&self.w, &cross(&planar_hit, &self.v));
+        let beta = dot(&self.w, &cross(&self.u, &planar_hit));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+
+        Some((t, alpha, beta))
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let (t, alpha, beta) = self.intersect(ray, ray_t)?;
+
+        // Rebuilding p from the edges keeps it on the plane up to a few roundings, however far
+        // along the ray the hit was.
+        let p = self.q + alpha * self.u + beta * self.v;
+        let p_error = gamma(7) * (abs(self.q) + abs(alpha * self.u) + abs(beta * self.v));
+
+        let mut hit_record = HitRecord {
+            t,
+            u: alpha,
+            v: beta,
+            p,
+            p_error,
+            dpdu: self.u,
+            dpdv: self.v,
+            dndu: Vec3::new(),
+            dndv: Vec3::new(),
+            footprint: Default::default(),
+            mat: self.mat.clone(),
+            normal: Default::default(),
+            shading_normal: Default::default(),
+            front_face: Default::default(),
+            light: false,
+        };
+
+        hit_record.set_face_normal(ray, self.normal);
+
+        Some(hit_record)
+    }
+
+    fn occluded(&self, ray: &Ray, ray_t: Interval) -> bool {
+        self.intersect(ray, ray_t).is_some()
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let Some((t, _, _)) = self.intersect(&Ray::new(origin, direction), Interval::POSITIVE) else {
+            return 0.0;
+        };
+        // Uniform by area, converted to solid angle.
+        let distance_squared = t * t * direction.length_squared();
+        let cosine = dot(&direction, &self.normal).abs() / direction.length();
+        distance_squared / (cosine * self.area)
+    }
+
+    fn sample(&self, origin: Point3) -> Vec3 {
+        self.q + random_float() * self.u + random_float() * self.v - origin
+    }
+}
+
+/// The closed axis-aligned box with opposite corners `a` and `b`, its six faces pointing outward.
+pub fn make_box(a: Point3, b: Point3, mat: Arc<dyn Material>) -> HittableList {
+    let min = Point3::with_values(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z()));
+    let max = Point3::with_values(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z()));
+
+    let dx = Vec3::with_values(max.x() - min.x(), 0.0, 0.0);
+    let dy = Vec3::with_values(0.0, max.y() - min.y(), 0.0);
+    let dz = Vec3::with_values(0.0, 0.0, max.z() - min.z());
+
+    let mut sides = HittableList::new();
+    sides.add(Box::new(Quad::new(Point3::with_values(min.x(), min.y(), max.z()), dx, dy, mat.clone())));
+    sides.add(Box::new(Quad::new(Point3::with_values(max.x(), min.y(), max.z()), -dz, dy, mat.clone())));
+    sides.add(Box::new(Quad::new(Point3::with_values(max.x(), min.y(), min.z()), -dx, dy, mat.clone())));
+    sides.add(Box::new(Quad::new(Point3::with_values(min.x(), min.y(), min.z()), dz, dy, mat.clone())));
+    sides.add(Box::new(Quad::new(Point3::with_values(min.x(), max.y(), max.z()), dx, -dz, mat.clone())));
+    sides.add(Box::new(Quad::new(Point3::with_values(min.x(), min.y(), min.z()), dx, dz, mat)));
+    sides
+}
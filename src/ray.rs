@@ -2,19 +2,30 @@ use crate::vec3::Vec3;
 
 pub type Point3 = Vec3;
 
-#[derive(Default)]
+/// Two auxiliary rays offset by one pixel in x and y on the film. They let textures estimate how much
+/// of the surface a pixel covers; directions are unit length.
+#[derive(Clone, Copy, Default)]
+pub struct RayDifferential {
+    pub rx_origin: Point3,
+    pub rx_direction: Vec3,
+    pub ry_origin: Point3,
+    pub ry_direction: Vec3,
+}
+
+#[derive(Clone, Default)]
 pub struct Ray {
     origin: Point3,
     direction: Vec3,
+    differential: Option<RayDifferential>,
 }
 
 impl Ray {
     pub fn new(origin: Point3, direction: Vec3) -> Self {
-        Ray { origin, direction }
+        Ray { origin, direction, differential: None }
     }
 
-    pub fn with_values(origin: Point3, direction: Vec3) -> Self {
-        Ray { origin, direction }
+    pub fn with_differential(origin: Point3, direction: Vec3, differential: Option<RayDifferential>) -> Self {
+        Ray { origin, direction, differential }
     }
 
     pub fn origin(&self) -> Point3 {
@@ -25,6 +36,10 @@ impl Ray {
         self.direction
     }
 
+    pub fn differential(&self) -> Option<RayDifferential> {
+        self.differential
+    }
+
     pub fn at(&self, t: f64) -> Point3 {
         self.origin + t*self.direction
     }
@@ -0,0 +1,198 @@
+use std::sync::LazyLock;
+
+use crate::{color::Color, spectrum::{cie_xyz, film_rgb, VISIBLE_MAX, VISIBLE_MIN}, vec3::{cross, dot, Vec3}};
+
+/// Table nodes per dimension. Coarser than pbrt's 64, which is plenty for smooth fits and keeps the
+/// table quick to build on first use.
+const RESOLUTION: usize = 16;
+/// Wavelength spacing, in nanometers, of the quadrature used while fitting.
+const FIT_STEP: f64 = 5.0;
+
+/// A smooth spectrum in [0, 1], s(λ) = sigmoid(c₀x² + c₁x + c₂) with x the wavelength rescaled from
+/// the visible range to [0, 1] (Jakob and Hanika 2019).
+#[derive(Clone, Copy)]
+pub struct SigmoidPolynomial {
+    c: [f64; 3],
+}
+
+impl SigmoidPolynomial {
+    pub fn eval(&self, lambda: f64) -> f64 {
+        let x = (lambda - VISIBLE_MIN) / (VISIBLE_MAX - VISIBLE_MIN);
+        let v = (self.c[0] * x + self.c[1]) * x + self.c[2];
+        if v.is_infinite() {
+            return if v > 0.0 { 1.0 } else { 0.0 };
+        }
+        0.5 + v / (2.0 * f64::sqrt(1.0 + v * v))
+    }
+}
+
+/// The smooth spectrum whose color is `rgb`, read from a table of fitted coefficients. Components
+/// are clamped to [0, 1]; saturated colors outside what a reflectance can reach come out as the
+/// nearest fit.
+pub fn rgb_to_spectrum(rgb: Color) -> SigmoidPolynomial {
+    let rgb = Color::with_values(rgb.x().clamp(0.0, 1.0), rgb.y().clamp(0.0, 1.0), rgb.z().clamp(0.0, 1.0));
+    if rgb.x() == rgb.y() && rgb.y() == rgb.z() {
+        // Flat spectra have an exact solution, infinite at 0 and 1.
+        let r = rgb.x();
+        return SigmoidPolynomial { c: [0.0, 0.0, (r - 0.5) / f64::sqrt(r * (1.0 - r))] };
+    }
+    TABLE.lookup(rgb)
+}
+
+static TABLE: LazyLock<Table> = LazyLock::new(Table::fit);
+
+/// Coefficients over (largest channel, its value z, the other two channels relative to it), laid out
+/// like pbrt's `RGBToSpectrumTable`. The z nodes crowd toward 0 and 1 where fits change fastest.
+struct Table {
+    z_nodes: [f64; RESOLUTION],
+    coefficients: Vec<[f64; 3]>,
+}
+
+impl Table {
+    fn index(channel: usize, z: usize, y: usize, x: usize) -> usize {
+        ((channel * RESOLUTION + z) * RESOLUTION + y) * RESOLUTION + x
+    }
+
+    fn lookup(&self, rgb: Color) -> SigmoidPolynomial {
+        let channel = if rgb.x() > rgb.y() {
+            if rgb.x() > rgb.z() { 0 } else { 2 }
+        } else if rgb.y() > rgb.z() {
+            1
+        } else {
+            2
+        };
+
+        let z = rgb[channel];
+        let scale = (RESOLUTION - 1) as f64 / z;
+        let x = rgb[(channel + 1) % 3] * scale;
+        let y = rgb[(channel + 2) % 3] * scale;
+
+        let xi = usize::min(x as usize, RESOLUTION - 2);
+        let yi = usize::min(y as usize, RESOLUTION - 2);
+        let zi = self.z_nodes.partition_point(|&node| node <= z).clamp(1, RESOLUTION - 1) - 1;
+        let dx = x - xi as f64;
+        let dy = y - yi as f64;
+        let dz = (z - self.z_nodes[zi]) / (self.z_nodes[zi + 1] - self.z_nodes[zi]);
+
+        let mut c = [0.0; 3];
+        for (k, ck) in c.iter_mut().enumerate() {
+            let at = |dzi: usize, dyi: usize, dxi: usize| self.coefficients[Self::index(channel, zi + dzi, yi + dyi, xi + dxi)][k];
+            let lerp = |t: f64, a: f64, b: f64| (1.0 - t) * a + t * b;
+            *ck = lerp(
+                dz,
+                lerp(dy, lerp(dx, at(0, 0, 0), at(0, 0, 1)), lerp(dx, at(0, 1, 0), at(0, 1, 1))),
+                lerp(dy, lerp(dx, at(1, 0, 0), at(1, 0, 1)), lerp(dx, at(1, 1, 0), at(1, 1, 1))),
+            );
+        }
+        SigmoidPolynomial { c }
+    }
+
+    /// Fits every entry with Gauss-Newton iterations on the film color of the spectrum. Each column of
+    /// z values starts from a flat spectrum at a middling z and walks outward, seeding every fit with
+    /// its neighbour's solution, since saturated targets don't converge from scratch.
+    fn fit() -> Self {
+        let smoothstep = |t: f64| t * t * (3.0 - 2.0 * t);
+        let z_nodes: [f64; RESOLUTION] = std::array::from_fn(|k| smoothstep(smoothstep(k as f64 / (RESOLUTION - 1) as f64)));
+
+        let quadrature: Vec<(f64, Vec3)> = (0..((VISIBLE_MAX - VISIBLE_MIN) / FIT_STEP) as usize)
+            .map(|i| {
+                let lambda = VISIBLE_MIN + (i as f64 + 0.5) * FIT_STEP;
+                (lambda, FIT_STEP * cie_xyz(lambda))
+            })
+            .collect();
+
+        // Serial on purpose: the table is first forced from inside the render's rayon workers, and
+        // waiting on nested rayon work while holding the `LazyLock` can deadlock them.
+        let columns: Vec<(usize, usize, usize, Vec<[f64; 3]>)> = (0..3 * RESOLUTION * RESOLUTION)
+            .map(|n| {
+                let (channel, y, x) = (n / (RESOLUTION * RESOLUTION), (n / RESOLUTION) % RESOLUTION, n % RESOLUTION);
+                let target = |z: f64| {
+                    let mut rgb = Color::new();
+                    rgb[channel] = z;
+                    rgb[(channel + 1) % 3] = x as f64 / (RESOLUTION - 1) as f64 * z;
+                    rgb[(channel + 2) % 3] = y as f64 / (RESOLUTION - 1) as f64 * z;
+                    rgb
+                };
+
+                let mut column = vec![[0.0; 3]; RESOLUTION];
+                let start = RESOLUTION / 5;
+                let mut c = [0.0; 3];
+                for z in start..RESOLUTION {
+                    c = gauss_newton(c, target(z_nodes[z]), &quadrature);
+                    column[z] = c;
+                }
+                c = column[start];
+                for z in (0..start).rev() {
+                    c = gauss_newton(c, target(z_nodes[z]), &quadrature);
+                    column[z] = c;
+                }
+                (channel, y, x, column)
+            })
+            .collect();
+
+        let mut coefficients = vec![[0.0; 3]; 3 * RESOLUTION * RESOLUTION * RESOLUTION];
+        for (channel, y, x, column) in columns {
+            for (z, c) in column.into_iter().enumerate() {
+                coefficients[Self::index(channel, z, y, x)] = c;
+            }
+        }
+        Self { z_nodes, coefficients }
+    }
+}
+
+fn residual(c: [f64; 3], target: Color, quadrature: &[(f64, Vec3)]) -> Color {
+    let polynomial = SigmoidPolynomial { c };
+    let mut xyz = Vec3::new();
+    for &(lambda, weight) in quadrature {
+        xyz += polynomial.eval(lambda) * weight;
+    }
+    film_rgb(xyz) - target
+}
+
+fn gauss_newton(mut c: [f64; 3], target: Color, quadrature: &[(f64, Vec3)]) -> [f64; 3] {
+    const EPSILON: f64 = 1e-5;
+
+    for _ in 0..20 {
+        let r = residual(c, target, quadrature);
+        if r.length_squared() < 1e-12 {
+            break;
+        }
+
+        // Forward-difference Jacobian, one column per coefficient.
+        let columns: [Vec3; 3] = std::array::from_fn(|k| {
+            let mut shifted = c;
+            shifted[k] += EPSILON;
+            (residual(shifted, target, quadrature) - r) / EPSILON
+        });
+
+        let det = dot_cross(columns[0], columns[1], columns[2]);
+        if det.abs() < 1e-15 {
+            break;
+        }
+        // Cramer's rule for J * step = r.
+        let step = [
+            dot_cross(r, columns[1], columns[2]) / det,
+            dot_cross(columns[0], r, columns[2]) / det,
+            dot_cross(columns[0], columns[1], r) / det,
+        ];
+
+        // Back off while the full step overshoots; stop once no step helps.
+        let error = r.length_squared();
+        let improved = (0..8)
+            .map(|i| {
+                let scale = 0.5f64.powi(i);
+                [c[0] - scale * step[0], c[1] - scale * step[1], c[2] - scale * step[2]]
+            })
+            .find(|&candidate| residual(candidate, target, quadrature).length_squared() < error);
+        match improved {
+            Some(candidate) => c = candidate,
+            None => break,
+        }
+    }
+    c
+}
+
+/// Determinant of the matrix with columns `a`, `b` and `c`.
+fn dot_cross(a: Vec3, b: Vec3, c: Vec3) -> f64 {
+    dot(&a, &cross(&b, &c))
+}
@@ -0,0 +1,53 @@
+use std::f64::consts::PI;
+
+use crate::vec3::Vec3;
+
+/// Maps a uniform sample in [0, 1)² to the unit disk, keeping strata compact (Shirley-Chiu).
+pub fn sample_concentric_disk(u: (f64, f64)) -> (f64, f64) {
+    let (ox, oy) = (2.0 * u.0 - 1.0, 2.0 * u.1 - 1.0);
+    if ox == 0.0 && oy == 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let (r, theta) = if ox.abs() > oy.abs() {
+        (ox, PI / 4.0 * (oy / ox))
+    } else {
+        (oy, PI / 2.0 - PI / 4.0 * (ox / oy))
+    };
+    (r * theta.cos(), r * theta.sin())
+}
+
+/// Cosine-weighted direction around +z; the pdf is `cos θ / π`.
+pub fn sample_cosine_hemisphere(u: (f64, f64)) -> Vec3 {
+    let (x, y) = sample_concentric_disk(u);
+    let z = f64::sqrt(f64::max(0.0, 1.0 - x * x - y * y));
+    Vec3::with_values(x, y, z)
+}
+
+pub fn sample_uniform_sphere(u: (f64, f64)) -> Vec3 {
+    let z = 1.0 - 2.0 * u.0;
+    let r = f64::sqrt(f64::max(0.0, 1.0 - z * z));
+    let phi = 2.0 * PI * u.1;
+    Vec3::with_values(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// Maps a uniform sample in [0, 1)² to the unit disk in polar coordinates. Cheaper than the concentric
+/// mapping, but distorts strata.
+pub fn sample_uniform_disk_polar(u: (f64, f64)) -> (f64, f64) {
+    let r = u.0.sqrt();
+    let theta = 2.0 * PI * u.1;
+    (r * theta.cos(), r * theta.sin())
+}
+
+/// Power heuristic (β = 2) weight of a sample from a strategy with density `f_pdf`, when a second
+/// strategy with density `g_pdf` could have produced it too (Veach 1997).
+pub fn power_heuristic(f_pdf: f64, g_pdf: f64) -> f64 {
+    let (f, g) = (f_pdf * f_pdf, g_pdf * g_pdf);
+    if f.is_infinite() {
+        return 1.0;
+    }
+    if f + g == 0.0 {
+        return 0.0;
+    }
+    f / (f + g)
+}
@@ -0,0 +1,1088 @@
+use std::sync::Arc;
+
+use crate::{alpha_cutout::AlphaCutout, camera::Camera, cloth::Cloth, color::Color, fresnel::ComplexIor, hair::Hair, hittable_list::HittableList, measured::Measured, medium::{Ior, Medium}, material::{BumpMapped, Coated, Conductor, Dielectric, DiffuseLight, Lambertian, Material, Metal, Mix, NormalMapped, RoughDielectric, Subsurface, ThinFilm}, principled::Principled, noise::{CloudTexture, GraniteTexture, MarbleTexture, Perlin, WoodTexture}, projection::{ProjectedTexture, Projection, TextureSpace}, shader_graph::{Node, ShaderGraph}, quad::{make_box, Quad}, ray::Point3, sphere::Sphere, mipmap::{FilterMode, WrapMode}, texture::{CheckerTexture, ImageTexture, SolidColor, Texture, UdimTexture}, texture_cache::TextureCache, triangle::Triangle, utils::{random_float, random_float_range}, vec3::{self, Vec3}};
+
+const OBJS_RANGE: i32 = 22;
+
+pub fn bouncing_spheres() {
+    let material_ground = Arc::new(Lambertian::new(Color::with_values(0.5, 0.5, 0.5)));
+
+    let mut world = HittableList::new();
+
+    world.add(Box::new(Sphere::new(
+        Point3::with_values(0.0, -1000., 0.0),
+        1000.0,
+        material_ground,
+    )));
+
+    for obj_a in -OBJS_RANGE/2..OBJS_RANGE/2 {
+        for obj_b in -OBJS_RANGE/2..OBJS_RANGE/2 {
+            let choose_mat = random_float();
+            let center = Point3::with_values(obj_a as f64 + 0.9*random_float(), 0.2, obj_b as f64 + 0.9*random_float());
+            if (center - Point3::with_values(4.0, 0.2, 0.0)).length() > 0.9 {
+                if choose_mat < 0.8 {
+                    let albedo = Color::random() * Color::random();
+                    let sphere_material = Arc::new(Lambertian::new(albedo));
+                    world.add(Box::new(Sphere::new(center, 0.2, sphere_material)));
+                } else if choose_mat < 0.95 {
+                    let albedo = Color::random_range(0.5, 1.0);
+                    let fuzz = random_float_range(0.0, 0.5);
+                    let sphere_material = Arc::new(Metal::new(albedo, fuzz));
+                    world.add(Box::new(Sphere::new(center, 0.2, sphere_material)));
+                } else {
+                    let sphere_material = Arc::new(Dielectric::new(1.5));
+                    world.add(Box::new(Sphere::new(center, 0.2, sphere_material)));
+                }
+            }
+
+        }
+    }
+
+    let material1 = Arc::new(Dielectric::new(1.5));
+    world.add(Box::new(Sphere::new(
+        Point3::with_values(0.0, 1.0, 0.0),
+        1.0,
+        material1,
+    )));
+
+    let material2 = Arc::new(Lambertian::new(Color::with_values(0.4, 0.2, 0.1)));
+    world.add(Box::new(Sphere::new(
+        Point3::with_values(-4.0, 1.0, 0.0),
+        1.0,
+        material2,
+    )));
+
+    let material3 = Arc::new(Metal::new(Color::with_values(0.7, 0.6, 0.5), 0.0));
+    world.add(Box::new(Sphere::new(
+        Point3::with_values(4.0, 1.0, 0.0),
+        1.0,
+        material3,
+    )));
+
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 1200;
+    cam.samples_per_pixel = 500;
+    cam.max_depth = 50;
+    cam.look_from = Point3::with_values(13.0, 2.0, 3.0);
+    cam.look_at = Point3::with_values(0.0, 0.0, 0.0);
+    cam.vup = Vec3::with_values(0.0, 1.0, 0.0);
+    cam.vfov = 20.0;
+    cam.defocus_angle = 0.6;
+    cam.focus_distance = 10.0;
+
+    cam.render(&world, &HittableList::new());
+}
+
+/// A checkered floor running off to the horizon and a map lying almost edge-on, both seen directly and
+/// through a mirror and a glass ball; the settings that used to shimmer without filtering.
+/// Expects `earthmap.jpg` in the working directory.
+pub fn texture_filtering() {
+    let mut world = HittableList::new();
+
+    let checker = Arc::new(CheckerTexture::from_colors(1.0, Color::with_values(0.1, 0.1, 0.1), Color::with_values(0.9, 0.9, 0.9)));
+    world.add(Box::new(Quad::new(
+        Point3::with_values(-500.0, 0.0, 500.0),
+        Vec3::with_values(1000.0, 0.0, 0.0),
+        Vec3::with_values(0.0, 0.0, -1000.0),
+        Arc::new(Lambertian::from_texture(checker)),
+    )));
+
+    let map = Arc::new(ImageTexture::load("earthmap.jpg", WrapMode::Clamp, FilterMode::Ewa).expect("could not load earthmap.jpg"));
+    world.add(Box::new(Quad::new(
+        Point3::with_values(-6.0, 0.01, -4.0),
+        Vec3::with_values(12.0, 0.0, 0.0),
+        Vec3::with_values(0.0, 0.0, -40.0),
+        Arc::new(Lambertian::from_texture(map)),
+    )));
+
+    world.add(Box::new(Sphere::new(Point3::with_values(-1.2, 1.0, 0.0), 1.0, Arc::new(Metal::new(Color::with_values(0.8, 0.8, 0.8), 0.0)))));
+    world.add(Box::new(Sphere::new(Point3::with_values(1.2, 1.0, 0.0), 1.0, Arc::new(Dielectric::new(1.5)))));
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 16;
+    cam.max_depth = 20;
+    cam.look_from = Point3::with_values(0.0, 1.5, 8.0);
+    cam.look_at = Point3::with_values(0.0, 0.8, 0.0);
+    cam.vup = Vec3::with_values(0.0, 1.0, 0.0);
+    cam.vfov = 45.0;
+    cam.defocus_angle = 0.0;
+
+    cam.render(&world, &HittableList::new());
+}
+
+pub fn procedural() {
+    let mut world = HittableList::new();
+
+    let marble = Arc::new(MarbleTexture::new(7, 4.0, Color::with_values(0.9, 0.9, 0.88), Color::with_values(0.2, 0.22, 0.25)));
+    let wood = Arc::new(WoodTexture::new(11, 6.0, Color::with_values(0.75, 0.55, 0.3), Color::with_values(0.35, 0.2, 0.08)));
+    let granite = Arc::new(GraniteTexture::new(13, 8.0, Color::with_values(0.7, 0.6, 0.55), Color::with_values(0.1, 0.1, 0.1)));
+    let clouds = Arc::new(CloudTexture::new(17, 1.5, 0.5, Color::with_values(0.3, 0.5, 0.9), Color::with_values(1.0, 1.0, 1.0)));
+
+    world.add(Box::new(Sphere::new(Point3::with_values(0.0, -1000.0, 0.0), 1000.0, Arc::new(Lambertian::from_texture(marble)))));
+    world.add(Box::new(Sphere::new(Point3::with_values(-2.2, 1.0, 0.0), 1.0, Arc::new(Lambertian::from_texture(wood)))));
+    world.add(Box::new(Sphere::new(Point3::with_values(0.0, 1.0, 0.0), 1.0, Arc::new(Lambertian::from_texture(granite)))));
+    let glow = Arc::new(DiffuseLight::from_texture(clouds));
+    let mut lights = HittableList::new();
+    world.add_light(&mut lights, Box::new(Sphere::new(Point3::with_values(2.2, 1.0, 0.0), 1.0, glow)));
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+    cam.look_from = Point3::with_values(0.0, 3.0, 12.0);
+    cam.look_at = Point3::with_values(0.0, 1.0, 0.0);
+    cam.vup = Vec3::with_values(0.0, 1.0, 0.0);
+    cam.vfov = 35.0;
+    cam.defocus_angle = 0.0;
+
+    cam.render(&world, &lights);
+}
+
+/// Normal and bump maps over plain materials. Expects a tangent-space `normalmap.png` in the working
+/// directory.
+pub fn surface_detail() {
+    let mut world = HittableList::new();
+
+    // Floor as a two-triangle mesh, its (u, v) tiled so the bumps stay small.
+    let cells = Arc::new(GraniteTexture::new(3, 2.0, Color::with_values(1.0, 1.0, 1.0), Color::with_values(0.0, 0.0, 0.0)));
+    let floor = Arc::new(BumpMapped::new(Arc::new(Lambertian::new(Color::with_values(0.6, 0.55, 0.5))), cells, 0.05));
+    let corners = [
+        Point3::with_values(-10.0, 0.0, 10.0),
+        Point3::with_values(10.0, 0.0, 10.0),
+        Point3::with_values(10.0, 0.0, -10.0),
+        Point3::with_values(-10.0, 0.0, -10.0),
+    ];
+    world.add(Box::new(Triangle::with_uvs([corners[0], corners[1], corners[2]], [(0.0, 0.0), (8.0, 0.0), (8.0, 8.0)], floor.clone())));
+    world.add(Box::new(Triangle::with_uvs([corners[0], corners[2], corners[3]], [(0.0, 0.0), (8.0, 8.0), (0.0, 8.0)], floor)));
+
+    let normal_map = Arc::new(ImageTexture::load_linear("normalmap.png", WrapMode::Repeat, FilterMode::Trilinear).expect("could not load normalmap.png"));
+    let hammered = Arc::new(NormalMapped::new(Arc::new(Metal::new(Color::with_values(0.8, 0.6, 0.4), 0.05)), normal_map));
+    world.add(Box::new(Sphere::new(Point3::with_values(-2.2, 1.0, 0.0), 1.0, hammered)));
+
+    let veins = Arc::new(MarbleTexture::new(5, 6.0, Color::with_values(1.0, 1.0, 1.0), Color::with_values(0.0, 0.0, 0.0)));
+    let carved = Arc::new(BumpMapped::new(Arc::new(Lambertian::new(Color::with_values(0.8, 0.8, 0.75))), veins, 0.05));
+    world.add(Box::new(Sphere::new(Point3::with_values(0.0, 1.0, 0.0), 1.0, carved)));
+
+    let ripples = Arc::new(CloudTexture::new(9, 4.0, 0.5, Color::with_values(0.0, 0.0, 0.0), Color::with_values(1.0, 1.0, 1.0)));
+    let rough_glass = Arc::new(BumpMapped::new(Arc::new(Dielectric::new(1.5)), ripples, 0.005));
+    let apex = Point3::with_values(2.2, 2.0, 0.0);
+    let base = [
+        Point3::with_values(1.2, 0.0, 1.0),
+        Point3::with_values(3.2, 0.0, 1.0),
+        Point3::with_values(3.2, 0.0, -1.0),
+        Point3::with_values(1.2, 0.0, -1.0),
+    ];
+    for i in 0..4 {
+        world.add(Box::new(Triangle::new(base[i], base[(i + 1) % 4], apex, rough_glass.clone())));
+    }
+    world.add(Box::new(Triangle::new(base[0], base[3], base[2], rough_glass.clone())));
+    world.add(Box::new(Triangle::new(base[0], base[2], base[1], rough_glass)));
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+    cam.look_from = Point3::with_values(0.0, 3.0, 9.0);
+    cam.look_at = Point3::with_values(0.0, 0.8, 0.0);
+    cam.vup = Vec3::with_values(0.0, 1.0, 0.0);
+    cam.vfov = 35.0;
+    cam.defocus_angle = 0.0;
+
+    cam.render(&world, &HittableList::new());
+}
+
+/// Textures placed by generated coordinates instead of (u, v): a triplanar floor and mesh cube, and
+/// the four projections side by side. Expects `earthmap.jpg` in the working directory.
+pub fn projections() {
+    let mut world = HittableList::new();
+
+    let map = Arc::new(ImageTexture::load("earthmap.jpg", WrapMode::Repeat, FilterMode::Trilinear).expect("could not load earthmap.jpg"));
+    let triplanar = |texture: Arc<dyn Texture>, space: TextureSpace, scale: f64| {
+        Arc::new(Lambertian::from_texture(Arc::new(ProjectedTexture::new(texture, Projection::Triplanar { sharpness: 4.0 }, space, scale))))
+    };
+
+    world.add(Box::new(Quad::new(
+        Point3::with_values(-20.0, 0.0, 20.0),
+        Vec3::with_values(40.0, 0.0, 0.0),
+        Vec3::with_values(0.0, 0.0, -40.0),
+        triplanar(map.clone(), TextureSpace::World, 0.25),
+    )));
+
+    // A mesh cube without any (u, v) of its own.
+    let wood = Arc::new(WoodTexture::new(11, 6.0, Color::with_values(0.75, 0.55, 0.3), Color::with_values(0.35, 0.2, 0.08)));
+    let cube_center = Point3::with_values(-3.3, 0.75, 0.0);
+    let cube = triplanar(wood, TextureSpace::object(cube_center, Vec3::with_values(0.3, 1.0, 0.0)), 1.0);
+    let corner = |x: f64, y: f64, z: f64| cube_center + 0.75 * Vec3::with_values(x, y, z);
+    let faces = [
+        [corner(-1.0, -1.0, 1.0), corner(1.0, -1.0, 1.0), corner(1.0, 1.0, 1.0), corner(-1.0, 1.0, 1.0)],
+        [corner(1.0, -1.0, -1.0), corner(-1.0, -1.0, -1.0), corner(-1.0, 1.0, -1.0), corner(1.0, 1.0, -1.0)],
+        [corner(1.0, -1.0, 1.0), corner(1.0, -1.0, -1.0), corner(1.0, 1.0, -1.0), corner(1.0, 1.0, 1.0)],
+        [corner(-1.0, -1.0, -1.0), corner(-1.0, -1.0, 1.0), corner(-1.0, 1.0, 1.0), corner(-1.0, 1.0, -1.0)],
+        [corner(-1.0, 1.0, 1.0), corner(1.0, 1.0, 1.0), corner(1.0, 1.0, -1.0), corner(-1.0, 1.0, -1.0)],
+        [corner(-1.0, -1.0, -1.0), corner(1.0, -1.0, -1.0), corner(1.0, -1.0, 1.0), corner(-1.0, -1.0, 1.0)],
+    ];
+    for face in faces {
+        world.add(Box::new(Triangle::new(face[0], face[1], face[2], cube.clone())));
+        world.add(Box::new(Triangle::new(face[0], face[2], face[3], cube.clone())));
+    }
+
+    let projected = |projection: Projection, center: Point3, scale: f64| {
+        let texture = Arc::new(ProjectedTexture::new(map.clone(), projection, TextureSpace::object(center, Vec3::with_values(0.0, 1.0, 0.0)), scale));
+        Box::new(Sphere::new(center, 0.75, Arc::new(Lambertian::from_texture(texture))))
+    };
+    world.add(projected(Projection::Planar, Point3::with_values(-1.1, 0.75, 0.0), 0.5));
+    world.add(projected(Projection::Cylindrical, Point3::with_values(1.1, 0.75, 0.0), 0.5));
+    world.add(projected(Projection::Spherical, Point3::with_values(3.3, 0.75, 0.0), 1.0));
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+    cam.look_from = Point3::with_values(0.0, 3.0, 10.0);
+    cam.look_at = Point3::with_values(0.0, 0.6, 0.0);
+    cam.vup = Vec3::with_values(0.0, 1.0, 0.0);
+    cam.vfov = 35.0;
+    cam.defocus_angle = 0.0;
+
+    cam.render(&world, &HittableList::new());
+}
+
+/// A 2x2 UDIM set (tiles 1001, 1002, 1011, 1012) on a mesh panel, plus a tangent-space normal map set
+/// for the same layout, sharing one cache. Expects `udim/albedo.<UDIM>.png` and
+/// `udim/normal.<UDIM>.png` under the working directory.
+pub fn udim() {
+    let mut world = HittableList::new();
+
+    let cache = Arc::new(TextureCache::new(256 << 20));
+    let albedo = Arc::new(UdimTexture::new("udim/albedo.<UDIM>.png", cache.clone(), FilterMode::Trilinear));
+    let normals = Arc::new(UdimTexture::new_linear("udim/normal.<UDIM>.png", cache, FilterMode::Trilinear));
+    let panel = Arc::new(NormalMapped::new(Arc::new(Lambertian::from_texture(albedo)), normals));
+
+    let corners = [
+        Point3::with_values(-2.0, 0.0, 0.0),
+        Point3::with_values(2.0, 0.0, 0.0),
+        Point3::with_values(2.0, 4.0, 0.0),
+        Point3::with_values(-2.0, 4.0, 0.0),
+    ];
+    world.add(Box::new(Triangle::with_uvs([corners[0], corners[1], corners[2]], [(0.0, 0.0), (2.0, 0.0), (2.0, 2.0)], panel.clone())));
+    world.add(Box::new(Triangle::with_uvs([corners[0], corners[2], corners[3]], [(0.0, 0.0), (2.0, 2.0), (0.0, 2.0)], panel)));
+
+    world.add(Box::new(Sphere::new(Point3::with_values(0.0, -1000.0, 0.0), 1000.0, Arc::new(Lambertian::new(Color::with_values(0.5, 0.5, 0.5))))));
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+    cam.look_from = Point3::with_values(3.0, 2.5, 9.0);
+    cam.look_at = Point3::with_values(0.0, 2.0, 0.0);
+    cam.vup = Vec3::with_values(0.0, 1.0, 0.0);
+    cam.vfov = 35.0;
+    cam.defocus_angle = 0.0;
+
+    cam.render(&world, &HittableList::new());
+}
+
+/// Glaze look for the middle sphere when no graph file is given on the command line.
+const GLAZE_GRAPH: &str = "
+# Mottled ceramic glaze: a color ramp over broad noise, darkened by fine speckles.
+p = position
+mottle = noise p 4 4 3
+glaze = ramp mottle 0:0.1,0.25,0.5 0.5:0.2,0.45,0.7 1:0.6,0.75,0.85
+fine = noise p 40 2 8
+speckle = remap fine 0.6 0.7 1 0.3
+look = multiply glaze speckle
+output look
+";
+
+/// Materials driven by shader graphs: rusty metal built in Rust, where one noise field drives both the
+/// albedo and the fuzz, a graph parsed from text, and a checker-painted triangle whose back face is
+/// shaded differently via the facing input. `graph_path` replaces the text graph with one from a file.
+pub fn shader_graph(graph_path: Option<String>) {
+    let mut world = HittableList::new();
+
+    world.add(Box::new(Sphere::new(Point3::with_values(0.0, -1000.0, 0.0), 1000.0, Arc::new(Lambertian::new(Color::with_values(0.5, 0.5, 0.5))))));
+
+    // Rust: noise decides where rust replaces bare steel, for the color and the roughness alike.
+    let mut albedo = ShaderGraph::new();
+    let position = albedo.add(Node::Position);
+    let noise = albedo.add(Node::Noise { input: position, noise: Perlin::new(21), scale: 3.0, octaves: 5 });
+    let amount = albedo.add(Node::Remap { input: noise, from: (0.45, 0.6), to: (0.0, 1.0) });
+    let steel = albedo.add(Node::Constant(Color::with_values(0.8, 0.8, 0.85)));
+    let rust = albedo.add(Node::Constant(Color::with_values(0.45, 0.18, 0.06)));
+    albedo.add(Node::Mix { a: steel, b: rust, factor: amount });
+
+    let mut fuzz = ShaderGraph::new();
+    let position = fuzz.add(Node::Position);
+    let noise = fuzz.add(Node::Noise { input: position, noise: Perlin::new(21), scale: 3.0, octaves: 5 });
+    let amount = fuzz.add(Node::Remap { input: noise, from: (0.45, 0.6), to: (0.02, 1.0) });
+    fuzz.set_output(amount);
+
+    world.add(Box::new(Sphere::new(Point3::with_values(-2.2, 1.0, 0.0), 1.0, Arc::new(Metal::from_textures(Arc::new(albedo), Arc::new(fuzz))))));
+
+    let middle = match graph_path {
+        Some(path) => ShaderGraph::load(&path).unwrap_or_else(|e| panic!("{path}: {e}")),
+        None => ShaderGraph::parse(GLAZE_GRAPH).expect("built-in graph is valid"),
+    };
+    world.add(Box::new(Sphere::new(Point3::with_values(0.0, 1.0, 0.0), 1.0, Arc::new(Lambertian::from_texture(Arc::new(middle))))));
+
+    let mut painted = ShaderGraph::new();
+    let checker = painted.add(Node::Texture(Arc::new(CheckerTexture::from_colors(0.25, Color::with_values(0.9, 0.1, 0.1), Color::with_values(0.9, 0.9, 0.9)))));
+    let back = painted.add(Node::Constant(Color::with_values(0.1, 0.1, 0.1)));
+    let facing = painted.add(Node::Facing);
+    let normal = painted.add(Node::Normal);
+    let tint = painted.add(Node::Remap { input: normal, from: (-1.0, 1.0), to: (0.6, 1.0) });
+    let front = painted.add(Node::Multiply(checker, tint));
+    painted.add(Node::Mix { a: back, b: front, factor: facing });
+    world.add(Box::new(Triangle::new(
+        Point3::with_values(1.4, 0.0, -0.5),
+        Point3::with_values(3.2, 0.2, 0.8),
+        Point3::with_values(2.2, 2.2, 0.0),
+        Arc::new(Lambertian::from_texture(Arc::new(painted))),
+    )));
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+    cam.look_from = Point3::with_values(0.0, 3.0, 9.0);
+    cam.look_at = Point3::with_values(0.0, 0.8, 0.0);
+    cam.vup = Vec3::with_values(0.0, 1.0, 0.0);
+    cam.vfov = 35.0;
+    cam.defocus_angle = 0.0;
+
+    cam.render(&world, &HittableList::new());
+}
+
+pub fn metals() {
+    let mut world = HittableList::new();
+
+    let checker = Arc::new(CheckerTexture::from_colors(0.5, Color::with_values(0.2, 0.2, 0.2), Color::with_values(0.7, 0.7, 0.7)));
+    world.add(Box::new(Quad::new(
+        Point3::with_values(-50.0, 0.0, 50.0),
+        Vec3::with_values(100.0, 0.0, 0.0),
+        Vec3::with_values(0.0, 0.0, -100.0),
+        Arc::new(Lambertian::from_texture(checker)),
+    )));
+
+    let presets = [
+        ComplexIor::gold(),
+        ComplexIor::copper(),
+        ComplexIor::aluminum(),
+        ComplexIor::silver(),
+        ComplexIor::platinum(),
+        ComplexIor::iron(),
+        ComplexIor::chromium(),
+    ];
+    for (i, ior) in presets.into_iter().enumerate() {
+        let x = -6.0 + 2.0 * i as f64;
+        world.add(Box::new(Sphere::new(Point3::with_values(x, 0.8, 0.0), 0.8, Arc::new(Conductor::new(ior, 0.25)))));
+    }
+
+    // Front row: a mirror, brushed metal with its grain along the sphere's parallels, and roughness
+    // that alternates in a checker pattern.
+    world.add(Box::new(Sphere::new(Point3::with_values(-2.5, 0.8, 2.5), 0.8, Arc::new(Conductor::new(ComplexIor::gold(), 0.0)))));
+    world.add(Box::new(Sphere::new(Point3::with_values(0.0, 0.8, 2.5), 0.8, Arc::new(Conductor::anisotropic(ComplexIor::aluminum(), 0.1, 0.6)))));
+    let roughness = Arc::new(CheckerTexture::from_colors(0.2, Color::with_values(0.05, 0.05, 0.05), Color::with_values(0.5, 0.5, 0.5)));
+    world.add(Box::new(Sphere::new(
+        Point3::with_values(2.5, 0.8, 2.5),
+        0.8,
+        Arc::new(Conductor::from_textures(ComplexIor::copper(), roughness.clone(), roughness)),
+    )));
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 800;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 20;
+    cam.look_from = Point3::with_values(0.0, 3.0, 11.0);
+    cam.look_at = Point3::with_values(0.0, 0.7, 0.5);
+    cam.vup = Vec3::with_values(0.0, 1.0, 0.0);
+    cam.vfov = 45.0;
+    cam.defocus_angle = 0.0;
+
+    cam.render(&world, &HittableList::new());
+}
+
+pub fn glass() {
+    let mut world = HittableList::new();
+
+    let floor = Arc::new(CheckerTexture::from_colors(0.5, Color::with_values(0.2, 0.2, 0.2), Color::with_values(0.8, 0.8, 0.8)));
+    world.add(Box::new(Quad::new(
+        Point3::with_values(-50.0, 0.0, 50.0),
+        Vec3::with_values(100.0, 0.0, 0.0),
+        Vec3::with_values(0.0, 0.0, -100.0),
+        Arc::new(Lambertian::from_texture(floor)),
+    )));
+
+    // A striped backdrop close behind the spheres shows how much each one blurs what it refracts.
+    let stripes = Arc::new(CheckerTexture::from_colors(0.25, Color::with_values(0.8, 0.1, 0.1), Color::with_values(0.9, 0.9, 0.8)));
+    world.add(Box::new(Quad::new(
+        Point3::with_values(-8.0, 0.0, -1.5),
+        Vec3::with_values(16.0, 0.0, 0.0),
+        Vec3::with_values(0.0, 5.0, 0.0),
+        Arc::new(Lambertian::from_texture(stripes)),
+    )));
+
+    world.add(Box::new(Sphere::new(Point3::with_values(-4.4, 0.9, 0.0), 0.9, Arc::new(Dielectric::new(1.5)))));
+    for (i, roughness) in [0.1, 0.3, 0.6].into_iter().enumerate() {
+        let x = -2.2 + 2.2 * i as f64;
+        world.add(Box::new(Sphere::new(Point3::with_values(x, 0.9, 0.0), 0.9, Arc::new(RoughDielectric::new(1.5, roughness)))));
+    }
+    let frosting = Arc::new(CheckerTexture::from_colors(0.15, Color::new(), Color::with_values(0.5, 0.5, 0.5)));
+    world.add(Box::new(Sphere::new(Point3::with_values(4.4, 0.9, 0.0), 0.9, Arc::new(RoughDielectric::from_texture(1.5, frosting)))));
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 800;
+    cam.samples_per_pixel = 200;
+    cam.max_depth = 30;
+    cam.look_from = Point3::with_values(0.0, 2.0, 10.0);
+    cam.look_at = Point3::with_values(0.0, 0.9, 0.0);
+    cam.vup = Vec3::with_values(0.0, 1.0, 0.0);
+    cam.vfov = 45.0;
+    cam.defocus_angle = 0.0;
+
+    cam.render(&world, &HittableList::new());
+}
+
+pub fn nested_dielectrics() {
+    let mut world = HittableList::new();
+
+    let floor = Arc::new(CheckerTexture::from_colors(0.5, Color::with_values(0.2, 0.2, 0.2), Color::with_values(0.8, 0.8, 0.8)));
+    world.add(Box::new(Quad::new(
+        Point3::with_values(-50.0, 0.0, 50.0),
+        Vec3::with_values(100.0, 0.0, 0.0),
+        Vec3::with_values(0.0, 0.0, -100.0),
+        Arc::new(Lambertian::from_texture(floor)),
+    )));
+    let stripes = Arc::new(CheckerTexture::from_colors(0.25, Color::with_values(0.1, 0.1, 0.4), Color::with_values(0.9, 0.9, 0.8)));
+    world.add(Box::new(Quad::new(
+        Point3::with_values(-8.0, 0.0, -3.0),
+        Vec3::with_values(16.0, 0.0, 0.0),
+        Vec3::with_values(0.0, 6.0, 0.0),
+        Arc::new(Lambertian::from_texture(stripes)),
+    )));
+
+    // A glass of water with an ice cube. Every volume overlaps its neighbours a little and the
+    // priorities settle who owns the overlap: the air pocket hollows out the glass and sits on the
+    // water, the ice displaces both, and the glass walls clip the water.
+    let glass = Arc::new(Dielectric::with_medium(Medium::new(1.5, Color::with_values(0.05, 0.0, 0.05), 1)));
+    let water = Arc::new(Dielectric::with_medium(Medium::new(1.33, Color::with_values(0.6, 0.15, 0.05), 2)));
+    let air = Arc::new(Dielectric::with_medium(Medium::new(1.0, Color::new(), 3)));
+    let ice = Arc::new(Dielectric::with_medium(Medium::new(1.31, Color::new(), 4)));
+    world.add(Box::new(make_box(Point3::with_values(-1.0, 0.0, -1.0), Point3::with_values(1.0, 2.4, 1.0), glass)));
+    world.add(Box::new(make_box(Point3::with_values(-0.9, 0.15, -0.9), Point3::with_values(0.9, 1.5, 0.9), water)));
+    world.add(Box::new(make_box(Point3::with_values(-0.85, 1.4, -0.85), Point3::with_values(0.85, 2.6, 0.85), air)));
+    world.add(Box::new(make_box(Point3::with_values(-0.3, 1.15, -0.3), Point3::with_values(0.3, 1.75, 0.3), ice)));
+
+    // Colored glass gets its color from absorption alone, so thicker parts are more saturated.
+    let ruby = Arc::new(Dielectric::with_medium(Medium::new(1.5, Color::with_values(0.1, 1.5, 1.5), 0)));
+    world.add(Box::new(Sphere::new(Point3::with_values(2.6, 0.8, 0.5), 0.8, ruby)));
+    let roughness = Arc::new(SolidColor::new(Color::with_values(0.3, 0.3, 0.3)));
+    let jade = Arc::new(RoughDielectric::with_medium(Medium::new(1.5, Color::with_values(1.2, 0.2, 0.9), 0), roughness));
+    world.add(Box::new(Sphere::new(Point3::with_values(-2.6, 0.8, 0.5), 0.8, jade)));
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 800;
+    cam.samples_per_pixel = 300;
+    cam.max_depth = 40;
+    cam.look_from = Point3::with_values(2.0, 3.5, 8.0);
+    cam.look_at = Point3::with_values(0.0, 1.0, 0.0);
+    cam.vup = Vec3::with_values(0.0, 1.0, 0.0);
+    cam.vfov = 40.0;
+    cam.defocus_angle = 0.0;
+
+    cam.render(&world, &HittableList::new());
+}
+
+/// With `spectral`, renders through the spectral pipeline, where every path disperses on its own
+/// wavelengths instead of tinting RGB.
+pub fn dispersion(spectral: bool) {
+    let mut world = HittableList::new();
+
+    let floor = Arc::new(CheckerTexture::from_colors(0.5, Color::with_values(0.2, 0.2, 0.2), Color::with_values(0.8, 0.8, 0.8)));
+    world.add(Box::new(Quad::new(
+        Point3::with_values(-50.0, 0.0, 50.0),
+        Vec3::with_values(100.0, 0.0, 0.0),
+        Vec3::with_values(0.0, 0.0, -100.0),
+        Arc::new(Lambertian::from_texture(floor)),
+    )));
+    // Fine black and white detail behind the glass makes the colored fringes easy to see.
+    let backdrop = Arc::new(CheckerTexture::from_colors(0.15, Color::with_values(0.02, 0.02, 0.02), Color::with_values(0.95, 0.95, 0.95)));
+    world.add(Box::new(Quad::new(
+        Point3::with_values(-8.0, 0.0, -2.0),
+        Vec3::with_values(16.0, 0.0, 0.0),
+        Vec3::with_values(0.0, 6.0, 0.0),
+        Arc::new(Lambertian::from_texture(backdrop)),
+    )));
+
+    // A dense flint prism lying along x.
+    let flint: Arc<dyn Material> = Arc::new(Dielectric::new(Ior::dense_flint()));
+    let prism_inside = Point3::with_values(0.0, 0.4, 0.5);
+    let section = |x: f64| [Point3::with_values(x, 0.0, 1.2), Point3::with_values(x, 0.0, -0.2), Point3::with_values(x, 1.2, 0.5)];
+    let (left, right) = (section(-1.3), section(1.3));
+    add_outward_triangle(&mut world, left, prism_inside, flint.clone());
+    add_outward_triangle(&mut world, right, prism_inside, flint.clone());
+    for i in 0..3 {
+        let j = (i + 1) % 3;
+        add_outward_triangle(&mut world, [left[i], left[j], right[j]], prism_inside, flint.clone());
+        add_outward_triangle(&mut world, [left[i], right[j], right[i]], prism_inside, flint.clone());
+    }
+
+    // An octahedral diamond, stretched upward like a double-ended crystal.
+    let diamond: Arc<dyn Material> = Arc::new(Dielectric::new(Ior::diamond()));
+    let gem_center = Point3::with_values(-3.0, 1.2, 0.5);
+    let tips = [
+        Vec3::with_values(0.8, 0.0, 0.0),
+        Vec3::with_values(0.0, 0.0, 0.8),
+        Vec3::with_values(-0.8, 0.0, 0.0),
+        Vec3::with_values(0.0, 0.0, -0.8),
+    ];
+    for i in 0..4 {
+        let (a, b) = (gem_center + tips[i], gem_center + tips[(i + 1) % 4]);
+        add_outward_triangle(&mut world, [a, b, gem_center + Vec3::with_values(0.0, 1.0, 0.0)], gem_center, diamond.clone());
+        add_outward_triangle(&mut world, [a, b, gem_center - Vec3::with_values(0.0, 1.0, 0.0)], gem_center, diamond.clone());
+    }
+
+    world.add(Box::new(Sphere::new(Point3::with_values(3.0, 0.8, 0.5), 0.8, Arc::new(Dielectric::new(Ior::bk7())))));
+    world.add(Box::new(Sphere::new(Point3::with_values(-1.5, 0.4, 2.2), 0.4, Arc::new(Dielectric::new(Ior::fused_silica())))));
+    // Acrylic, from its Cauchy fit.
+    let acrylic = Ior::Cauchy { a: 1.4767, b: 0.00318 };
+    world.add(Box::new(Sphere::new(Point3::with_values(1.6, 0.4, 2.2), 0.4, Arc::new(Dielectric::new(acrylic)))));
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 800;
+    cam.samples_per_pixel = 400;
+    cam.max_depth = 40;
+    cam.look_from = Point3::with_values(0.0, 2.5, 9.0);
+    cam.look_at = Point3::with_values(0.0, 0.9, 0.5);
+    cam.vup = Vec3::with_values(0.0, 1.0, 0.0);
+    cam.vfov = 42.0;
+    cam.defocus_angle = 0.0;
+    cam.spectral = spectral;
+
+    cam.render(&world, &HittableList::new());
+}
+
+/// Adds a face of a closed convex mesh, wound so its normal points away from `inside`.
+fn add_outward_triangle(world: &mut HittableList, vertices: [Point3; 3], inside: Point3, mat: Arc<dyn Material>) {
+    let normal = vec3::cross(&(vertices[1] - vertices[0]), &(vertices[2] - vertices[0]));
+    if vec3::dot(&normal, &(vertices[0] - inside)) < 0.0 {
+        world.add(Box::new(Triangle::new(vertices[0], vertices[2], vertices[1], mat)));
+    } else {
+        world.add(Box::new(Triangle::new(vertices[0], vertices[1], vertices[2], mat)));
+    }
+}
+
+pub fn principled() {
+    let mut world = HittableList::new();
+
+    let floor = Arc::new(CheckerTexture::from_colors(0.5, Color::with_values(0.2, 0.2, 0.2), Color::with_values(0.8, 0.8, 0.8)));
+    world.add(Box::new(Quad::new(
+        Point3::with_values(-50.0, 0.0, 50.0),
+        Vec3::with_values(100.0, 0.0, 0.0),
+        Vec3::with_values(0.0, 0.0, -100.0),
+        Arc::new(Lambertian::from_texture(floor)),
+    )));
+
+    let gray = |value: f64| -> Arc<dyn Texture> { Arc::new(SolidColor::gray(value)) };
+    let color = |r: f64, g: f64, b: f64| -> Arc<dyn Texture> { Arc::new(SolidColor::new(Color::with_values(r, g, b))) };
+    let mut add = |x: f64, z: f64, material: Principled| {
+        world.add(Box::new(Sphere::new(Point3::with_values(x, 0.5, z), 0.5, Arc::new(material))));
+    };
+
+    // Back row: gold with increasing roughness. Middle row: the same for red plastic.
+    for i in 0..5 {
+        let roughness = i as f64 / 4.0;
+        let x = -2.4 + 1.2 * i as f64;
+        add(x, -2.4, Principled { base_color: color(1.0, 0.78, 0.34), metallic: gray(1.0), roughness: gray(roughness), ..Default::default() });
+        add(x, -1.2, Principled { base_color: color(0.8, 0.1, 0.1), roughness: gray(roughness), ..Default::default() });
+    }
+
+    // Front row: one sphere per remaining parameter.
+    let front = [
+        Principled { base_color: color(0.3, 0.05, 0.2), roughness: gray(1.0), sheen: 1.0, ..Default::default() },
+        Principled { base_color: color(0.05, 0.1, 0.5), roughness: gray(0.8), clearcoat: 1.0, clearcoat_gloss: 0.9, ..Default::default() },
+        Principled { base_color: color(0.9, 0.6, 0.5), roughness: gray(0.6), subsurface: 1.0, ..Default::default() },
+        Principled { base_color: color(0.9, 0.95, 1.0), roughness: gray(0.15), transmission: 1.0, ..Default::default() },
+        Principled { base_color: color(0.1, 0.6, 0.2), roughness: gray(0.2), specular: 1.0, specular_tint: 1.0, ..Default::default() },
+    ];
+    for (i, material) in front.into_iter().enumerate() {
+        add(-2.4 + 1.2 * i as f64, 0.0, material);
+    }
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 800;
+    cam.samples_per_pixel = 200;
+    cam.max_depth = 30;
+    cam.look_from = Point3::with_values(0.0, 3.0, 6.0);
+    cam.look_at = Point3::with_values(0.0, 0.3, -1.1);
+    cam.vup = Vec3::with_values(0.0, 1.0, 0.0);
+    cam.vfov = 45.0;
+    cam.defocus_angle = 0.0;
+
+    cam.render(&world, &HittableList::new());
+}
+
+pub fn iridescence() {
+    let mut world = HittableList::new();
+
+    // Film thickness in nanometers, swirling the way soap drains and oil spreads.
+    let swirl = |seed, scale, to| {
+        let mut graph = ShaderGraph::new();
+        let position = graph.add(Node::Position);
+        let noise = graph.add(Node::Noise { input: position, noise: Perlin::new(seed), scale, octaves: 4 });
+        let thickness = graph.add(Node::Remap { input: noise, from: (0.0, 1.0), to });
+        graph.set_output(thickness);
+        Arc::new(graph)
+    };
+
+    // An oil slick on dark steel.
+    let oil = ThinFilm::from_textures(swirl(3, 0.8, (100.0, 900.0)), Arc::new(SolidColor::gray(1.47)));
+    world.add(Box::new(Quad::new(
+        Point3::with_values(-50.0, 0.0, 50.0),
+        Vec3::with_values(100.0, 0.0, 0.0),
+        Vec3::with_values(0.0, 0.0, -100.0),
+        Arc::new(Conductor::with_thin_film(ComplexIor::iron(), 0.05, oil)),
+    )));
+
+    // Soap bubbles: a film of soapy water with air on both sides.
+    for (i, (center, radius)) in [((-3.0, 1.4, 0.0), 1.2), ((-1.2, 2.6, -2.0), 0.8), ((-4.2, 2.9, -1.5), 0.6)].into_iter().enumerate() {
+        let soap = ThinFilm::from_textures(swirl(10 + i as u64, 2.0, (250.0, 750.0)), Arc::new(SolidColor::gray(1.33)));
+        world.add(Box::new(Sphere::new(
+            Point3::with_values(center.0, center.1, center.2),
+            radius,
+            Arc::new(Dielectric::with_thin_film(Medium::clear(1.0), soap)),
+        )));
+    }
+
+    // Anodized titanium: the oxide thickness sets the color.
+    for (i, thickness) in [180.0, 240.0, 300.0].into_iter().enumerate() {
+        let z = -1.5 + 1.5 * i as f64;
+        let oxide = ThinFilm::new(thickness, 2.4);
+        world.add(Box::new(Sphere::new(Point3::with_values(2.0, 0.6, z), 0.6, Arc::new(Conductor::with_thin_film(ComplexIor::iron(), 0.2, oxide)))));
+    }
+
+    // Heat-tinted steel: a temper oxide over a plain albedo metal.
+    let temper = ThinFilm::new(320.0, 2.2);
+    world.add(Box::new(Sphere::new(Point3::with_values(0.0, 0.7, 1.5), 0.7, Arc::new(Metal::with_thin_film(Color::with_values(0.6, 0.6, 0.62), 0.1, temper)))));
+
+    // Glass with a quarter-wave magnesium fluoride anti-reflection coating, next to bare glass.
+    let coating = ThinFilm::new(100.0, 1.38);
+    world.add(Box::new(Sphere::new(Point3::with_values(4.2, 0.8, -0.8), 0.8, Arc::new(Dielectric::with_thin_film(Medium::clear(1.5), coating)))));
+    world.add(Box::new(Sphere::new(Point3::with_values(4.2, 0.8, 1.0), 0.8, Arc::new(Dielectric::new(1.5)))));
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 800;
+    cam.samples_per_pixel = 200;
+    cam.max_depth = 30;
+    cam.look_from = Point3::with_values(0.0, 3.0, 10.0);
+    cam.look_at = Point3::with_values(0.0, 1.0, 0.0);
+    cam.vup = Vec3::with_values(0.0, 1.0, 0.0);
+    cam.vfov = 45.0;
+    cam.defocus_angle = 0.0;
+
+    cam.render(&world, &HittableList::new());
+}
+
+pub fn coated() {
+    let mut world = HittableList::new();
+
+    let floor = Arc::new(CheckerTexture::from_colors(0.5, Color::with_values(0.2, 0.2, 0.2), Color::with_values(0.8, 0.8, 0.8)));
+    world.add(Box::new(Quad::new(
+        Point3::with_values(-50.0, 0.0, 50.0),
+        Vec3::with_values(100.0, 0.0, 0.0),
+        Vec3::with_values(0.0, 0.0, -100.0),
+        Arc::new(Lambertian::from_texture(floor)),
+    )));
+
+    let red = Color::with_values(0.7, 0.05, 0.05);
+    let paints: [Arc<dyn Material>; 5] = [
+        // Bare paint, for comparison.
+        Arc::new(Lambertian::new(red)),
+        // Solid car paint: a glossy clearcoat over the pigment.
+        Arc::new(Coated::new(Arc::new(Lambertian::new(red)), 1.5, 0.0)),
+        // Metallic car paint: clearcoat over a rough tinted metal.
+        Arc::new(Coated::new(Arc::new(Conductor::new(ComplexIor::copper(), 0.35)), 1.5, 0.0)),
+        // Satin finish: a rough coat.
+        Arc::new(Coated::new(Arc::new(Lambertian::new(red)), 1.5, 0.3)),
+        // Varnished wood: the amber varnish darkens and warms what it covers.
+        Arc::new(Coated::from_texture(
+            Arc::new(Lambertian::from_texture(Arc::new(WoodTexture::new(11, 6.0, Color::with_values(0.75, 0.55, 0.3), Color::with_values(0.35, 0.2, 0.08))))),
+            1.5,
+            Arc::new(SolidColor::gray(0.05)),
+            Color::with_values(0.05, 0.2, 0.6),
+        )),
+    ];
+    for (i, paint) in paints.into_iter().enumerate() {
+        let x = -4.4 + 2.2 * i as f64;
+        world.add(Box::new(Sphere::new(Point3::with_values(x, 0.9, 0.0), 0.9, paint)));
+    }
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 800;
+    cam.samples_per_pixel = 200;
+    cam.max_depth = 30;
+    cam.look_from = Point3::with_values(0.0, 2.0, 10.0);
+    cam.look_at = Point3::with_values(0.0, 0.9, 0.0);
+    cam.vup = Vec3::with_values(0.0, 1.0, 0.0);
+    cam.vfov = 45.0;
+    cam.defocus_angle = 0.0;
+
+    cam.render(&world, &HittableList::new());
+}
+
+pub fn subsurface() {
+    let mut world = HittableList::new();
+
+    let floor = Arc::new(CheckerTexture::from_colors(0.5, Color::with_values(0.2, 0.2, 0.2), Color::with_values(0.8, 0.8, 0.8)));
+    world.add(Box::new(Quad::new(
+        Point3::with_values(-50.0, 0.0, 50.0),
+        Vec3::with_values(100.0, 0.0, 0.0),
+        Vec3::with_values(0.0, 0.0, -100.0),
+        Arc::new(Lambertian::from_texture(floor)),
+    )));
+
+    // A light low behind the spheres shows how far light bleeds through each.
+    let light = Arc::new(DiffuseLight::new(Color::with_values(6.0, 6.0, 6.0)));
+    let mut lights = HittableList::new();
+    world.add_light(&mut lights, Box::new(Sphere::new(Point3::with_values(0.0, 1.5, -6.0), 1.0, light)));
+
+    let materials: [Arc<dyn Material>; 5] = [
+        // Plaster, for comparison.
+        Arc::new(Lambertian::new(Color::with_values(0.8, 0.8, 0.8))),
+        Arc::new(Subsurface::new(1.5, Color::with_values(0.999, 0.999, 0.998), Color::with_values(0.08, 0.08, 0.08), 0.1)),
+        Arc::new(Subsurface::new(1.45, Color::with_values(0.998, 0.99, 0.95), Color::with_values(0.3, 0.25, 0.2), 0.3)),
+        Arc::new(Subsurface::new(1.33, Color::with_values(0.9995, 0.9995, 0.999), Color::with_values(0.02, 0.025, 0.03), 0.0)),
+        Arc::new(Subsurface::new(1.4, Color::with_values(0.99, 0.93, 0.85), Color::with_values(0.4, 0.15, 0.08), 0.35)),
+    ];
+    // Plaster, marble, wax, milk and skin.
+    for (i, material) in materials.into_iter().enumerate() {
+        let x = -4.4 + 2.2 * i as f64;
+        world.add(Box::new(Sphere::new(Point3::with_values(x, 0.9, 0.0), 0.9, material)));
+    }
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 800;
+    cam.samples_per_pixel = 200;
+    cam.max_depth = 30;
+    cam.look_from = Point3::with_values(0.0, 2.0, 10.0);
+    cam.look_at = Point3::with_values(0.0, 0.9, 0.0);
+    cam.vup = Vec3::with_values(0.0, 1.0, 0.0);
+    cam.vfov = 45.0;
+    cam.defocus_angle = 0.0;
+
+    cam.render(&world, &lights);
+}
+
+pub fn cutout() {
+    let mut world = HittableList::new();
+
+    world.add(Box::new(Quad::new(
+        Point3::with_values(-50.0, 0.0, 50.0),
+        Vec3::with_values(100.0, 0.0, 0.0),
+        Vec3::with_values(0.0, 0.0, -100.0),
+        Arc::new(Lambertian::new(Color::with_values(0.35, 0.45, 0.25))),
+    )));
+
+    // A lattice fence: a single quad with the checker's dark squares cut away.
+    let lattice = Arc::new(CheckerTexture::from_colors(0.2, Color::new(), Color::with_values(1.0, 1.0, 1.0)));
+    let fence = Quad::new(
+        Point3::with_values(-6.0, 0.0, -2.05),
+        Vec3::with_values(12.0, 0.0, 0.0),
+        Vec3::with_values(0.0, 2.05, 0.0),
+        Arc::new(Lambertian::new(Color::with_values(0.6, 0.45, 0.3))),
+    );
+    world.add(Box::new(AlphaCutout::new(Box::new(fence), lattice)));
+
+    // Leaves: quads shaped by the alpha of leaf.png.
+    let leaf_alpha = Arc::new(ImageTexture::load_alpha("leaf.png", WrapMode::Clamp, FilterMode::Bilinear).expect("could not load leaf.png"));
+    let leaf_color = Arc::new(ImageTexture::load("leaf.png", WrapMode::Clamp, FilterMode::Bilinear).expect("could not load leaf.png"));
+    let leaf_material = Arc::new(Lambertian::from_texture(leaf_color));
+    for i in 0..7 {
+        let angle = i as f64 * 0.9;
+        let corner = Point3::with_values(-3.5 + 0.9 * i as f64, 0.4 + 0.25 * (i % 3) as f64, 0.8 + 0.3 * (i % 2) as f64);
+        let leaf = Quad::new(
+            corner,
+            0.9 * Vec3::with_values(angle.cos(), 0.0, angle.sin()),
+            Vec3::with_values(-0.2, 0.9, 0.3),
+            leaf_material.clone(),
+        );
+        world.add(Box::new(AlphaCutout::new(Box::new(leaf), leaf_alpha.clone())));
+    }
+
+    // A decal: the leaf printed onto a plaster block by mixing in red paint through its alpha.
+    let plaster: Arc<dyn Material> = Arc::new(Lambertian::new(Color::with_values(0.8, 0.8, 0.75)));
+    let paint: Arc<dyn Material> = Arc::new(Lambertian::new(Color::with_values(0.7, 0.05, 0.05)));
+    world.add(Box::new(Quad::new(
+        Point3::with_values(2.5, 0.0, 0.0),
+        Vec3::with_values(1.8, 0.0, 0.0),
+        Vec3::with_values(0.0, 1.8, 0.0),
+        Arc::new(Mix::from_texture(plaster.clone(), paint, leaf_alpha)),
+    )));
+    world.add(Box::new(make_box(Point3::with_values(2.5, 0.0, -0.4), Point3::with_values(4.3, 1.8, -0.01), plaster)));
+
+    // Patchy rust over polished iron, mixed by noise.
+    let mut patches = ShaderGraph::new();
+    let position = patches.add(Node::Position);
+    let noise = patches.add(Node::Noise { input: position, noise: Perlin::new(5), scale: 2.5, octaves: 5 });
+    let amount = patches.add(Node::Remap { input: noise, from: (0.45, 0.55), to: (0.0, 1.0) });
+    patches.set_output(amount);
+    let rusty = Mix::from_texture(
+        Arc::new(Conductor::new(ComplexIor::iron(), 0.1)),
+        Arc::new(Lambertian::new(Color::with_values(0.45, 0.18, 0.06))),
+        Arc::new(patches),
+    );
+    world.add(Box::new(Sphere::new(Point3::with_values(0.2, 0.9, -0.5), 0.9, Arc::new(rusty))));
+
+    // Dusty gold: a constant blend, a third of it dust.
+    let dusty = Mix::new(Arc::new(Conductor::new(ComplexIor::gold(), 0.2)), Arc::new(Lambertian::new(Color::with_values(0.6, 0.55, 0.5))), 0.35);
+    world.add(Box::new(Sphere::new(Point3::with_values(-1.8, 0.6, 1.5), 0.6, Arc::new(dusty))));
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 800;
+    cam.samples_per_pixel = 200;
+    cam.max_depth = 30;
+    cam.look_from = Point3::with_values(0.0, 2.0, 10.0);
+    cam.look_at = Point3::with_values(0.0, 0.9, 0.0);
+    cam.vup = Vec3::with_values(0.0, 1.0, 0.0);
+    cam.vfov = 45.0;
+    cam.defocus_angle = 0.0;
+
+    cam.render(&world, &HittableList::new());
+}
+
+pub fn cloth_and_hair() {
+    let mut world = HittableList::new();
+
+    let floor = Arc::new(CheckerTexture::from_colors(0.5, Color::with_values(0.2, 0.2, 0.2), Color::with_values(0.8, 0.8, 0.8)));
+    world.add(Box::new(Quad::new(
+        Point3::with_values(-50.0, 0.0, 50.0),
+        Vec3::with_values(100.0, 0.0, 0.0),
+        Vec3::with_values(0.0, 0.0, -100.0),
+        Arc::new(Lambertian::from_texture(floor)),
+    )));
+
+    // Back row: red velvet, blue cotton, black velvet with a silvery sheen, and plain diffuse.
+    let fabrics: [Arc<dyn Material>; 4] = [
+        Arc::new(Cloth::new(Color::with_values(0.3, 0.02, 0.03), Color::with_values(1.0, 0.4, 0.4), 0.25)),
+        Arc::new(Cloth::new(Color::with_values(0.1, 0.2, 0.5), Color::with_values(0.5, 0.6, 0.8), 0.8)),
+        Arc::new(Cloth::new(Color::with_values(0.01, 0.01, 0.01), Color::with_values(0.8, 0.8, 0.8), 0.3)),
+        Arc::new(Lambertian::new(Color::with_values(0.3, 0.02, 0.03))),
+    ];
+    for (i, fabric) in fabrics.into_iter().enumerate() {
+        let x = -3.3 + 2.2 * i as f64;
+        world.add(Box::new(Sphere::new(Point3::with_values(x, 0.9, -1.5), 0.9, fabric)));
+    }
+
+    // Front row: balls of wound hair, blonde, red, brown and black, and dyed teal.
+    let hairs = [
+        Hair::from_melanin(0.3, 0.0, 0.3, 0.3),
+        Hair::from_melanin(0.3, 2.5, 0.3, 0.3),
+        Hair::from_melanin(1.3, 0.0, 0.3, 0.3),
+        Hair::from_melanin(8.0, 0.0, 0.3, 0.3),
+        Hair::from_color(Color::with_values(0.1, 0.5, 0.5), 0.3, 0.3),
+    ];
+    for (i, hair) in hairs.into_iter().enumerate() {
+        let x = -3.6 + 1.8 * i as f64;
+        world.add(Box::new(Sphere::new(Point3::with_values(x, 0.7, 1.2), 0.7, Arc::new(hair))));
+    }
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 800;
+    cam.samples_per_pixel = 200;
+    cam.max_depth = 30;
+    cam.look_from = Point3::with_values(0.0, 2.5, 10.0);
+    cam.look_at = Point3::with_values(0.0, 0.8, 0.0);
+    cam.vup = Vec3::with_values(0.0, 1.0, 0.0);
+    cam.vfov = 45.0;
+    cam.defocus_angle = 0.0;
+
+    cam.render(&world, &HittableList::new());
+}
+
+/// A measured BRDF from a MERL `.binary` file, between a diffuse and a metal sphere to compare it
+/// against. `brdf_path` defaults to the gold metallic paint from the MERL database.
+pub fn measured(brdf_path: Option<String>) {
+    let mut world = HittableList::new();
+
+    let floor = Arc::new(CheckerTexture::from_colors(0.5, Color::with_values(0.2, 0.2, 0.2), Color::with_values(0.8, 0.8, 0.8)));
+    world.add(Box::new(Quad::new(
+        Point3::with_values(-50.0, 0.0, 50.0),
+        Vec3::with_values(100.0, 0.0, 0.0),
+        Vec3::with_values(0.0, 0.0, -100.0),
+        Arc::new(Lambertian::from_texture(floor)),
+    )));
+
+    let path = brdf_path.unwrap_or_else(|| "gold-metallic-paint.binary".to_string());
+    let measured = Measured::load(&path).unwrap_or_else(|e| panic!("{path}: {e}"));
+    world.add(Box::new(Sphere::new(Point3::with_values(0.0, 1.2, 0.0), 1.2, Arc::new(measured))));
+    world.add(Box::new(Sphere::new(Point3::with_values(-2.8, 0.9, 0.0), 0.9, Arc::new(Lambertian::new(Color::with_values(0.5, 0.5, 0.5))))));
+    world.add(Box::new(Sphere::new(Point3::with_values(2.8, 0.9, 0.0), 0.9, Arc::new(Metal::new(Color::with_values(0.8, 0.8, 0.8), 0.2)))));
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 800;
+    cam.samples_per_pixel = 200;
+    cam.max_depth = 30;
+    cam.look_from = Point3::with_values(0.0, 2.0, 10.0);
+    cam.look_at = Point3::with_values(0.0, 0.9, 0.0);
+    cam.vup = Vec3::with_values(0.0, 1.0, 0.0);
+    cam.vfov = 45.0;
+    cam.defocus_angle = 0.0;
+
+    cam.render(&world, &HittableList::new());
+}
+
+/// Veach's test of multiple importance sampling: metal plates from nearly mirror-like to rough, each
+/// reflecting spherical lights from tiny and bright to large and dim, with a diffuse and a glass
+/// sphere in front. Light samples win on the rough plates and small lights, BSDF samples on the smooth
+/// plates and large lights.
+pub fn mis() {
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+
+    let look_from = Point3::with_values(0.0, 2.0, 14.0);
+    let light_row = Point3::with_values(0.0, 3.0, -3.0);
+
+    world.add(Box::new(Quad::new(
+        Point3::with_values(-50.0, -2.0, 50.0),
+        Vec3::with_values(100.0, 0.0, 0.0),
+        Vec3::with_values(0.0, 0.0, -100.0),
+        Arc::new(Lambertian::new(Color::with_values(0.3, 0.3, 0.3))),
+    )));
+
+    // Each plate is tilted to mirror the row of lights toward the camera.
+    for (i, fuzz) in [0.02, 0.06, 0.15, 0.35].into_iter().enumerate() {
+        let center = Point3::with_values(0.0, -1.2 + 0.45 * i as f64, 4.0 - 1.6 * i as f64);
+        let normal = vec3::unit_vector(&(vec3::unit_vector(&(look_from - center)) + vec3::unit_vector(&(light_row - center))));
+        let u = Vec3::with_values(9.0, 0.0, 0.0);
+        let v = 1.3 * vec3::unit_vector(&vec3::cross(&normal, &u));
+        let metal = Arc::new(Metal::new(Color::with_values(0.8, 0.8, 0.8), fuzz));
+        world.add(Box::new(Quad::new(center - 0.5 * u - 0.5 * v, u, v, metal)));
+    }
+
+    // Equal power: radiance falls with the square of the radius.
+    for (i, radius) in [0.03, 0.1, 0.3, 0.9].into_iter().enumerate() {
+        let center = light_row + Vec3::with_values(-3.75 + 2.5 * i as f64, 0.0, 0.0);
+        let light = Arc::new(DiffuseLight::new((0.25 / (radius * radius)) * Color::with_values(1.0, 1.0, 1.0)));
+        world.add_light(&mut lights, Box::new(Sphere::new(center, radius, light)));
+    }
+
+    world.add(Box::new(Sphere::new(Point3::with_values(-2.0, -1.3, 7.0), 0.7, Arc::new(Lambertian::new(Color::with_values(0.7, 0.3, 0.2))))));
+    world.add(Box::new(Sphere::new(Point3::with_values(2.0, -1.3, 7.0), 0.7, Arc::new(Dielectric::new(1.5)))));
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 800;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 30;
+    cam.background = Some(Color::with_values(0.05, 0.05, 0.05));
+    cam.look_from = look_from;
+    cam.look_at = Point3::with_values(0.0, -0.3, 1.0);
+    cam.vup = Vec3::with_values(0.0, 1.0, 0.0);
+    cam.vfov = 40.0;
+    cam.defocus_angle = 0.0;
+
+    cam.render(&world, &lights);
+}
+
+pub fn cornell_box() {
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+
+    let red = Arc::new(Lambertian::new(Color::with_values(0.65, 0.05, 0.05)));
+    let white = Arc::new(Lambertian::new(Color::with_values(0.73, 0.73, 0.73)));
+    let green = Arc::new(Lambertian::new(Color::with_values(0.12, 0.45, 0.15)));
+    let light = Arc::new(DiffuseLight::new(Color::with_values(15.0, 15.0, 15.0)));
+
+    world.add(Box::new(Quad::new(Point3::with_values(555.0, 0.0, 0.0), Vec3::with_values(0.0, 555.0, 0.0), Vec3::with_values(0.0, 0.0, 555.0), green)));
+    world.add(Box::new(Quad::new(Point3::with_values(0.0, 0.0, 0.0), Vec3::with_values(0.0, 555.0, 0.0), Vec3::with_values(0.0, 0.0, 555.0), red)));
+    world.add_light(&mut lights, Box::new(Quad::new(Point3::with_values(343.0, 554.0, 332.0), Vec3::with_values(-130.0, 0.0, 0.0), Vec3::with_values(0.0, 0.0, -105.0), light)));
+    world.add(Box::new(Quad::new(Point3::with_values(0.0, 0.0, 0.0), Vec3::with_values(555.0, 0.0, 0.0), Vec3::with_values(0.0, 0.0, 555.0), white.clone())));
+    world.add(Box::new(Quad::new(Point3::with_values(555.0, 555.0, 555.0), Vec3::with_values(-555.0, 0.0, 0.0), Vec3::with_values(0.0, 0.0, -555.0), white.clone())));
+    world.add(Box::new(Quad::new(Point3::with_values(0.0, 0.0, 555.0), Vec3::with_values(555.0, 0.0, 0.0), Vec3::with_values(0.0, 555.0, 0.0), white.clone())));
+
+    world.add(Box::new(Sphere::new(Point3::with_values(190.0, 90.0, 190.0), 90.0, Arc::new(Dielectric::new(1.5)))));
+    world.add(Box::new(Sphere::new(Point3::with_values(370.0, 120.0, 370.0), 120.0, white)));
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 1.0;
+    cam.image_width = 600;
+    cam.samples_per_pixel = 200;
+    cam.max_depth = 50;
+    cam.background = Some(Color::new());
+    cam.look_from = Point3::with_values(278.0, 278.0, -800.0);
+    cam.look_at = Point3::with_values(278.0, 278.0, 0.0);
+    cam.vup = Vec3::with_values(0.0, 1.0, 0.0);
+    cam.vfov = 40.0;
+    cam.defocus_angle = 0.0;
+
+    cam.render(&world, &lights);
+}
+
+pub fn checkered_spheres() {
+    let mut world = HittableList::new();
+
+    let checker = Arc::new(CheckerTexture::from_colors(0.32, Color::with_values(0.2, 0.3, 0.1), Color::with_values(0.9, 0.9, 0.9)));
+
+    world.add(Box::new(Sphere::new(Point3::with_values(0.0, -10.0, 0.0), 10.0, Arc::new(Lambertian::from_texture(checker.clone())))));
+    world.add(Box::new(Sphere::new(Point3::with_values(0.0, 10.0, 0.0), 10.0, Arc::new(Lambertian::from_texture(checker)))));
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+    cam.look_from = Point3::with_values(13.0, 2.0, 3.0);
+    cam.look_at = Point3::with_values(0.0, 0.0, 0.0);
+    cam.vup = Vec3::with_values(0.0, 1.0, 0.0);
+    cam.vfov = 20.0;
+    cam.defocus_angle = 0.0;
+
+    cam.render(&world, &HittableList::new());
+}
+
+/// Expects an equirectangular `earthmap.jpg` in the working directory.
+pub fn earth() {
+    let mut world = HittableList::new();
+
+    let earth_texture = Arc::new(ImageTexture::load("earthmap.jpg", WrapMode::Repeat, FilterMode::Trilinear).expect("could not load earthmap.jpg"));
+    world.add(Box::new(Sphere::new(Point3::with_values(0.0, 0.0, 0.0), 2.0, Arc::new(Lambertian::from_texture(earth_texture)))));
+
+    // The same map as a flat, backlit poster; clamping keeps the left and right edges from bleeding into each other.
+    let poster_texture = Arc::new(ImageTexture::load("earthmap.jpg", WrapMode::Clamp, FilterMode::Bilinear).expect("could not load earthmap.jpg"));
+    let poster = Arc::new(DiffuseLight::from_texture(poster_texture));
+    let mut lights = HittableList::new();
+    world.add_light(&mut lights, Box::new(Quad::new(Point3::with_values(-4.0, -2.0, -6.0), Vec3::with_values(8.0, 0.0, 0.0), Vec3::with_values(0.0, 4.0, 0.0), poster)));
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+    cam.look_from = Point3::with_values(0.0, 0.0, 12.0);
+    cam.look_at = Point3::with_values(0.0, 0.0, 0.0);
+    cam.vup = Vec3::with_values(0.0, 1.0, 0.0);
+    cam.vfov = 40.0;
+    cam.defocus_angle = 0.0;
+
+    cam.render(&world, &lights);
+}
@@ -0,0 +1,368 @@
+use std::{collections::HashMap, fmt, fs, io, path::Path, sync::Arc};
+
+use crate::{
+    color::Color,
+    mipmap::{FilterMode, WrapMode},
+    noise::Perlin,
+    texture::{ImageTexture, Texture, TextureContext},
+};
+
+/// Handle to a node of a `ShaderGraph`, returned by `ShaderGraph::add`.
+#[derive(Clone, Copy)]
+pub struct NodeId(usize);
+
+/// One operation of a shader graph. Every node produces a color; scalar inputs (mix factors, ramp
+/// positions, ...) read it through `Texture::scalar`, and scalar results are gray.
+pub enum Node {
+    Constant(Color),
+    Texture(Arc<dyn Texture>),
+    /// Hit point, as (x, y, z).
+    Position,
+    /// Outward shading normal, as (x, y, z).
+    Normal,
+    /// Surface (u, v), as (u, v, 0).
+    Uv,
+    /// 1 on the outer side of the surface, 0 on the inner one.
+    Facing,
+    /// Fractal Perlin noise of `input` (typically `Position`) scaled by `scale`, remapped to [0, 1].
+    Noise { input: NodeId, noise: Perlin, scale: f64, octaves: u32 },
+    /// (1 - factor) * a + factor * b.
+    Mix { a: NodeId, b: NodeId, factor: NodeId },
+    Multiply(NodeId, NodeId),
+    Add(NodeId, NodeId),
+    /// Linear map of each channel from `from` onto `to`, clamped to `to`. An empty `from` range is a
+    /// step from `to.0` to `to.1` at that value.
+    Remap { input: NodeId, from: (f64, f64), to: (f64, f64) },
+    /// Color ramp: piecewise linear through `stops` (position, color), sorted by position.
+    Ramp { input: NodeId, stops: Vec<(f64, Color)> },
+}
+
+impl Node {
+    fn inputs(&self) -> Vec<NodeId> {
+        match self {
+            Node::Noise { input, .. } | Node::Remap { input, .. } | Node::Ramp { input, .. } => vec![*input],
+            Node::Mix { a, b, factor } => vec![*a, *b, *factor],
+            Node::Multiply(a, b) | Node::Add(a, b) => vec![*a, *b],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Small node graph evaluated as a texture, so it can drive any parameter a material takes as a
+/// texture: albedos, roughnesses, `Metal` fuzz, bump heights and scales, film thicknesses, mix
+/// weights, emission, ... Parameters held as plain numbers can't vary: the indices of refraction
+/// and absorption of `Dielectric`, `RoughDielectric`, `Subsurface`, `Coated` and `Conductor`, the
+/// coefficients of `Hair`, and the scalar lobes of `Principled`. Nodes can only refer to nodes
+/// added before them, which keeps the graph acyclic by construction. Build it in Rust with `add`,
+/// or load it from the text format described at `parse`.
+pub struct ShaderGraph {
+    nodes: Vec<Node>,
+    output: usize,
+}
+
+impl ShaderGraph {
+    pub fn new() -> Self {
+        Self { nodes: Vec::new(), output: 0 }
+    }
+
+    /// Adds a node; the last one added is the output unless `set_output` says otherwise.
+    ///
+    /// Panics if the node refers to a node of another graph.
+    pub fn add(&mut self, node: Node) -> NodeId {
+        assert!(
+            node.inputs().iter().all(|input| input.0 < self.nodes.len()),
+            "shader graph node refers to a node that isn't in the graph"
+        );
+
+        self.nodes.push(node);
+        self.output = self.nodes.len() - 1;
+        NodeId(self.output)
+    }
+
+    pub fn set_output(&mut self, output: NodeId) {
+        self.output = output.0;
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, GraphError> {
+        Self::parse(&fs::read_to_string(path).map_err(GraphError::Io)?)
+    }
+
+    /// Parses a graph, one statement per line; `#` starts a comment:
+    ///
+    /// ```text
+    /// name = constant <color>
+    /// name = position | normal | uv | facing
+    /// name = image <path>
+    /// name = noise <input> <scale> <octaves> [<seed>]
+    /// name = mix <a> <b> <factor>
+    /// name = multiply <a> <b>
+    /// name = add <a> <b>
+    /// name = remap <input> <from_min> <from_max> <to_min> <to_max>
+    /// name = ramp <input> <position>:<color> ...
+    /// output <name>
+    /// ```
+    ///
+    /// Colors are `r,g,b` or a single gray value; inputs are names of earlier nodes. Images are read
+    /// as sRGB color, repeating, with trilinear filtering.
+    pub fn parse(source: &str) -> Result<Self, GraphError> {
+        let mut graph = ShaderGraph::new();
+        let mut names: HashMap<&str, NodeId> = HashMap::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let error = |message: String| GraphError::Parse { line: index + 1, message };
+            let line = line.split('#').next().unwrap_or("").trim();
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+
+            match tokens.as_slice() {
+                [] => continue,
+                ["output", name] => {
+                    let id = *names.get(name).ok_or_else(|| error(format!("unknown node `{name}`")))?;
+                    graph.set_output(id);
+                }
+                [name, "=", op, args @ ..] => {
+                    let input = |i: usize| -> Result<NodeId, GraphError> {
+                        let arg = args.get(i).ok_or_else(|| error(format!("`{op}` is missing argument {}", i + 1)))?;
+                        names.get(arg).copied().ok_or_else(|| error(format!("unknown node `{arg}`")))
+                    };
+                    let number = |i: usize| -> Result<f64, GraphError> {
+                        let arg = args.get(i).ok_or_else(|| error(format!("`{op}` is missing argument {}", i + 1)))?;
+                        arg.parse().map_err(|_| error(format!("`{arg}` is not a number")))
+                    };
+                    let expect_args = |count: std::ops::RangeInclusive<usize>| {
+                        if count.contains(&args.len()) {
+                            Ok(())
+                        } else {
+                            Err(error(format!("`{op}` takes {} to {} arguments, got {}", count.start(), count.end(), args.len())))
+                        }
+                    };
+
+                    let node = match *op {
+                        "constant" => {
+                            expect_args(1..=1)?;
+                            Node::Constant(parse_color(args[0]).ok_or_else(|| error(format!("`{}` is not a color", args[0])))?)
+                        }
+                        "position" => expect_args(0..=0).map(|_| Node::Position)?,
+                        "normal" => expect_args(0..=0).map(|_| Node::Normal)?,
+                        "uv" => expect_args(0..=0).map(|_| Node::Uv)?,
+                        "facing" => expect_args(0..=0).map(|_| Node::Facing)?,
+                        "image" => {
+                            expect_args(1..=1)?;
+                            let texture = ImageTexture::load(args[0], WrapMode::Repeat, FilterMode::Trilinear)
+                                .map_err(|e| error(format!("could not load `{}`: {e}", args[0])))?;
+                            Node::Texture(Arc::new(texture))
+                        }
+                        "noise" => {
+                            expect_args(3..=4)?;
+                            let seed = if args.len() == 4 { number(3)? as u64 } else { 0 };
+                            Node::Noise { input: input(0)?, noise: Perlin::new(seed), scale: number(1)?, octaves: number(2)? as u32 }
+                        }
+                        "mix" => {
+                            expect_args(3..=3)?;
+                            Node::Mix { a: input(0)?, b: input(1)?, factor: input(2)? }
+                        }
+                        "multiply" => {
+                            expect_args(2..=2)?;
+                            Node::Multiply(input(0)?, input(1)?)
+                        }
+                        "add" => {
+                            expect_args(2..=2)?;
+                            Node::Add(input(0)?, input(1)?)
+                        }
+                        "remap" => {
+                            expect_args(5..=5)?;
+                            let from = (number(1)?, number(2)?);
+                            if from.0 == from.1 {
+                                return Err(error(format!("`remap` range {} to {} is empty", from.0, from.1)));
+                            }
+                            Node::Remap { input: input(0)?, from, to: (number(3)?, number(4)?) }
+                        }
+                        "ramp" => {
+                            if args.len() < 2 {
+                                return Err(error("`ramp` needs an input and at least one stop".to_string()));
+                            }
+                            let mut stops = Vec::new();
+                            for stop in &args[1..] {
+                                let parsed = stop
+                                    .split_once(':')
+                                    .and_then(|(position, color)| Some((position.parse().ok()?, parse_color(color)?)));
+                                stops.push(parsed.ok_or_else(|| error(format!("`{stop}` is not a <position>:<color> stop")))?);
+                            }
+                            stops.sort_by(|a: &(f64, Color), b| a.0.total_cmp(&b.0));
+                            Node::Ramp { input: input(0)?, stops }
+                        }
+                        _ => return Err(error(format!("unknown node type `{op}`"))),
+                    };
+
+                    names.insert(name, graph.add(node));
+                }
+                _ => return Err(error(format!("expected `name = <node>` or `output <name>`, got `{line}`"))),
+            }
+        }
+
+        if graph.nodes.is_empty() {
+            return Err(GraphError::Parse { line: 0, message: "graph has no nodes".to_string() });
+        }
+        Ok(graph)
+    }
+
+    fn evaluate(&self, id: usize, ctx: &TextureContext, cache: &mut [Option<Color>]) -> Color {
+        if let Some(value) = cache[id] {
+            return value;
+        }
+
+        let mut input = |node: &NodeId| self.evaluate(node.0, ctx, cache);
+        let gray = |x: f64| Color::with_values(x, x, x);
+        let scalar = |c: Color| (c.x() + c.y() + c.z()) / 3.0;
+
+        let value = match &self.nodes[id] {
+            Node::Constant(color) => *color,
+            Node::Texture(texture) => texture.value(ctx),
+            Node::Position => ctx.p,
+            Node::Normal => ctx.normal,
+            Node::Uv => Color::with_values(ctx.u, ctx.v, 0.0),
+            Node::Facing => gray(if ctx.front_face { 1.0 } else { 0.0 }),
+            Node::Noise { input: source, noise, scale, octaves } => {
+                let p = *scale * input(source);
+                gray((0.5 * (1.0 + noise.fbm(&p, *octaves))).clamp(0.0, 1.0))
+            }
+            Node::Mix { a, b, factor } => {
+                let t = scalar(input(factor));
+                (1.0 - t) * input(a) + t * input(b)
+            }
+            Node::Multiply(a, b) => input(a) * input(b),
+            Node::Add(a, b) => input(a) + input(b),
+            Node::Remap { input: source, from, to } => {
+                let c = input(source);
+                let (lo, hi) = (f64::min(to.0, to.1), f64::max(to.0, to.1));
+                let map = |x: f64| {
+                    if from.0 == from.1 {
+                        return if x < from.0 { to.0 } else { to.1 };
+                    }
+                    (to.0 + (x - from.0) / (from.1 - from.0) * (to.1 - to.0)).clamp(lo, hi)
+                };
+                Color::with_values(map(c.x()), map(c.y()), map(c.z()))
+            }
+            Node::Ramp { input: source, stops } => {
+                let t = scalar(input(source));
+                match stops.iter().position(|(position, _)| *position > t) {
+                    None => stops.last().map_or(Color::new(), |stop| stop.1),
+                    Some(0) => stops[0].1,
+                    Some(i) => {
+                        let ((p0, c0), (p1, c1)) = (stops[i - 1], stops[i]);
+                        let f = (t - p0) / (p1 - p0);
+                        (1.0 - f) * c0 + f * c1
+                    }
+                }
+            }
+        };
+
+        cache[id] = Some(value);
+        value
+    }
+}
+
+impl Texture for ShaderGraph {
+    fn value(&self, ctx: &TextureContext) -> Color {
+        if self.nodes.is_empty() {
+            return Color::new();
+        }
+
+        let mut cache = vec![None; self.output + 1];
+        self.evaluate(self.output, ctx, &mut cache)
+    }
+}
+
+fn parse_color(text: &str) -> Option<Color> {
+    let components: Vec<f64> = text.split(',').map(|c| c.parse().ok()).collect::<Option<_>>()?;
+    match components.as_slice() {
+        [gray] => Some(Color::with_values(*gray, *gray, *gray)),
+        [r, g, b] => Some(Color::with_values(*r, *g, *b)),
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+pub enum GraphError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphError::Io(error) => write!(f, "could not read shader graph: {error}"),
+            GraphError::Parse { line, message } => write!(f, "shader graph line {line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::Point3;
+
+    fn parse_error(source: &str) -> (usize, String) {
+        match ShaderGraph::parse(source) {
+            Err(GraphError::Parse { line, message }) => (line, message),
+            Err(error) => panic!("unexpected error: {error}"),
+            Ok(_) => panic!("`{source}` parsed"),
+        }
+    }
+
+    fn value_at(graph: &ShaderGraph, u: f64, v: f64) -> Color {
+        graph.value(&TextureContext::new(u, v, Point3::new()))
+    }
+
+    #[test]
+    fn parses_a_valid_graph() {
+        let graph = ShaderGraph::parse(
+            "# (u, v), remapped and tinted
+             coords = uv
+             half = remap coords 0 1 0 0.5   # stretched down
+             tint = constant 1,0.5,0.25
+             out = multiply half tint
+             other = constant 0.3
+             output out",
+        )
+        .unwrap();
+
+        let value = value_at(&graph, 0.8, 0.6);
+        assert!((value - Color::with_values(0.4, 0.15, 0.0)).length() < 1e-12);
+    }
+
+    #[test]
+    fn the_last_node_is_the_default_output() {
+        let graph = ShaderGraph::parse("a = constant 0.2\nb = constant 0.7").unwrap();
+        assert_eq!(value_at(&graph, 0.0, 0.0).x(), 0.7);
+    }
+
+    #[test]
+    fn rejects_unknown_node_types_and_names() {
+        assert_eq!(parse_error("a = blur 1"), (1, "unknown node type `blur`".to_string()));
+        assert_eq!(parse_error("a = constant 1\nb = add a c"), (2, "unknown node `c`".to_string()));
+        assert_eq!(parse_error("a = constant 1\noutput b"), (2, "unknown node `b`".to_string()));
+    }
+
+    #[test]
+    fn rejects_cycles() {
+        // Inputs must be defined on an earlier line, so neither self-reference nor a loop through a
+        // later node can be written.
+        assert_eq!(parse_error("a = add a a"), (1, "unknown node `a`".to_string()));
+        assert_eq!(parse_error("a = uv\nb = add a c\nc = multiply b b"), (2, "unknown node `c`".to_string()));
+    }
+
+    #[test]
+    fn rejects_empty_remap_ranges() {
+        assert_eq!(parse_error("x = uv\ny = remap x 0.5 0.5 0 1"), (2, "`remap` range 0.5 to 0.5 is empty".to_string()));
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        assert_eq!(parse_error("a = remap"), (1, "`remap` takes 5 to 5 arguments, got 0".to_string()));
+        assert_eq!(parse_error("a = constant red"), (1, "`red` is not a color".to_string()));
+        assert_eq!(parse_error("a constant 1").0, 1);
+        assert_eq!(parse_error("# nothing here\n").1, "graph has no nodes");
+    }
+}
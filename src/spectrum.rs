@@ -0,0 +1,227 @@
+use std::{ops::{Add, AddAssign, Mul}, sync::LazyLock};
+
+use crate::{color::Color, rgb_to_spectrum::rgb_to_spectrum, vec3::Vec3};
+
+/// The range RGB paths pick their dispersion wavelength from, in nanometers.
+pub const WAVELENGTH_MIN: f64 = 380.0;
+pub const WAVELENGTH_MAX: f64 = 780.0;
+
+/// The range spectral paths sample and the film integrates over, in nanometers.
+pub const VISIBLE_MIN: f64 = 360.0;
+pub const VISIBLE_MAX: f64 = 830.0;
+
+/// Wavelengths each spectral path carries.
+const SPECTRUM_SAMPLES: usize = 4;
+
+/// Uniformly picks a visible wavelength for a path.
+pub fn sample_wavelength(u: f64) -> f64 {
+    WAVELENGTH_MIN + u * (WAVELENGTH_MAX - WAVELENGTH_MIN)
+}
+
+/// CIE 1931 color matching functions, from the multi-lobe Gaussian fit of Wyman, Sloan and Shirley
+/// (2013).
+pub fn cie_xyz(lambda: f64) -> Vec3 {
+    let g = |mu: f64, sigma_low: f64, sigma_high: f64| {
+        let t = (lambda - mu) / if lambda < mu { sigma_low } else { sigma_high };
+        f64::exp(-0.5 * t * t)
+    };
+
+    Vec3::with_values(
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    )
+}
+
+pub fn xyz_to_linear_srgb(xyz: Vec3) -> Color {
+    Color::with_values(
+        3.2404542 * xyz.x() - 1.5371385 * xyz.y() - 0.4985314 * xyz.z(),
+        -0.9692660 * xyz.x() + 1.8760108 * xyz.y() + 0.0415560 * xyz.z(),
+        0.0556434 * xyz.x() - 0.2040259 * xyz.y() + 1.0572252 * xyz.z(),
+    )
+}
+
+/// Per-channel averages of the clamped sRGB response over the sampled range.
+static MEAN_RESPONSE: LazyLock<Color> = LazyLock::new(|| {
+    let steps = (WAVELENGTH_MAX - WAVELENGTH_MIN) as usize;
+    let mut sum = Color::new();
+    for i in 0..steps {
+        sum += srgb_response(WAVELENGTH_MIN + i as f64 + 0.5);
+    }
+    sum / steps as f64
+});
+
+fn srgb_response(lambda: f64) -> Color {
+    let rgb = xyz_to_linear_srgb(cie_xyz(lambda));
+    Color::with_values(rgb.x().max(0.0), rgb.y().max(0.0), rgb.z().max(0.0))
+}
+
+/// The RGB a path contributes once it is restricted to the single wavelength `lambda`, scaled so it
+/// averages to white over uniformly sampled wavelengths. Out-of-gamut parts of the spectrum are
+/// clamped, trading some saturation for never producing negative radiance.
+pub fn wavelength_weight(lambda: f64) -> Color {
+    let mean = *MEAN_RESPONSE;
+    let rgb = srgb_response(lambda);
+    Color::with_values(rgb.x() / mean.x(), rgb.y() / mean.y(), rgb.z() / mean.z())
+}
+
+/// Linear sRGB of the equal-energy spectrum over the visible range, which the film maps to white.
+static EQUAL_ENERGY_WHITE: LazyLock<Color> = LazyLock::new(|| {
+    let mut xyz = Vec3::new();
+    let steps = (VISIBLE_MAX - VISIBLE_MIN) as usize;
+    for i in 0..steps {
+        xyz += cie_xyz(VISIBLE_MIN + i as f64 + 0.5);
+    }
+    xyz_to_linear_srgb(xyz)
+});
+
+/// The output color of a spectrum with tristimulus values `xyz`, white balanced so the equal-energy
+/// spectrum of unit value maps to (1, 1, 1). That keeps flat spectra and RGB white in agreement.
+pub fn film_rgb(xyz: Vec3) -> Color {
+    let rgb = xyz_to_linear_srgb(xyz);
+    let white = *EQUAL_ENERGY_WHITE;
+    Color::with_values(rgb.x() / white.x(), rgb.y() / white.y(), rgb.z() / white.z())
+}
+
+/// A spectral quantity at the wavelengths a path carries.
+#[derive(Clone, Copy, Default)]
+pub struct SampledSpectrum([f64; SPECTRUM_SAMPLES]);
+
+impl SampledSpectrum {
+    pub fn constant(value: f64) -> Self {
+        Self([value; SPECTRUM_SAMPLES])
+    }
+}
+
+impl Add for SampledSpectrum {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(std::array::from_fn(|i| self.0[i] + rhs.0[i]))
+    }
+}
+
+impl AddAssign for SampledSpectrum {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Mul for SampledSpectrum {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self(std::array::from_fn(|i| self.0[i] * rhs.0[i]))
+    }
+}
+
+/// How a path carries light: as RGB, or as a handful of sampled wavelengths. Materials and lights
+/// stay in RGB either way; the path converts their colors into its own representation.
+pub trait PathSpectrum {
+    type Value: Copy + Default + Add<Output = Self::Value> + AddAssign + Mul<Output = Self::Value>;
+
+    /// The wavelength, in nanometers, that anything dispersive should use.
+    fn wavelength(&self) -> f64;
+
+    fn upsample(&self, rgb: Color) -> Self::Value;
+
+    /// Restricts the path to `wavelength` alone, for when it crosses a dispersive interface, and
+    /// returns the weight that keeps the result unbiased.
+    fn single_wavelength(&mut self) -> Self::Value;
+
+    fn to_rgb(&self, value: Self::Value) -> Color;
+}
+
+/// The RGB pipeline. It still draws one wavelength, used only once the path disperses.
+pub struct RgbPath {
+    wavelength: f64,
+}
+
+impl RgbPath {
+    pub fn sample(u: f64) -> Self {
+        Self { wavelength: sample_wavelength(u) }
+    }
+}
+
+impl PathSpectrum for RgbPath {
+    type Value = Color;
+
+    fn wavelength(&self) -> f64 {
+        self.wavelength
+    }
+
+    fn upsample(&self, rgb: Color) -> Color {
+        rgb
+    }
+
+    fn single_wavelength(&mut self) -> Color {
+        wavelength_weight(self.wavelength)
+    }
+
+    fn to_rgb(&self, value: Color) -> Color {
+        value
+    }
+}
+
+/// The spectral pipeline: a hero wavelength and three more spread evenly across the visible range
+/// (Wilkie et al. 2014), each importance sampled toward where the eye is most sensitive. RGB colors
+/// become smooth spectra, and the film integrates against the CIE curves.
+pub struct SpectralPath {
+    lambda: [f64; SPECTRUM_SAMPLES],
+    pdf: [f64; SPECTRUM_SAMPLES],
+}
+
+impl SpectralPath {
+    pub fn sample(u: f64) -> Self {
+        let lambda: [f64; SPECTRUM_SAMPLES] = std::array::from_fn(|i| {
+            let up = (u + i as f64 / SPECTRUM_SAMPLES as f64).fract();
+            // Inverse CDF of a 1/cosh² falloff around 538 nm (pbrt, section 5.4.2).
+            538.0 - 138.888889 * f64::atanh(0.85691062 - 1.82750197 * up)
+        });
+        let pdf = lambda.map(|lambda| {
+            if !(VISIBLE_MIN..=VISIBLE_MAX).contains(&lambda) {
+                return 0.0;
+            }
+            0.0039398042 / f64::cosh(0.0072 * (lambda - 538.0)).powi(2)
+        });
+        Self { lambda, pdf }
+    }
+}
+
+impl PathSpectrum for SpectralPath {
+    type Value = SampledSpectrum;
+
+    fn wavelength(&self) -> f64 {
+        self.lambda[0]
+    }
+
+    /// Reflectance-like colors become spectra in [0, 1]; brighter ones, like light sources, are
+    /// scaled down into that range first and back up after.
+    fn upsample(&self, rgb: Color) -> SampledSpectrum {
+        let max = rgb.x().max(rgb.y()).max(rgb.z());
+        if max <= 0.0 {
+            return SampledSpectrum::default();
+        }
+
+        let scale = if max <= 1.0 { 1.0 } else { 2.0 * max };
+        let polynomial = rgb_to_spectrum(rgb / scale);
+        SampledSpectrum(self.lambda.map(|lambda| scale * polynomial.eval(lambda)))
+    }
+
+    fn single_wavelength(&mut self) -> SampledSpectrum {
+        // The hero wavelength now stands in for all of them.
+        self.pdf[0] /= SPECTRUM_SAMPLES as f64;
+        self.pdf[1..].fill(0.0);
+        SampledSpectrum::constant(1.0)
+    }
+
+    fn to_rgb(&self, value: SampledSpectrum) -> Color {
+        let mut xyz = Vec3::new();
+        for i in 0..SPECTRUM_SAMPLES {
+            if self.pdf[i] > 0.0 {
+                xyz += (value.0[i] / self.pdf[i]) * cie_xyz(self.lambda[i]);
+            }
+        }
+        film_rgb(xyz / SPECTRUM_SAMPLES as f64)
+    }
+}
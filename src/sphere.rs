@@ -1,6 +1,6 @@
-use std::sync::Arc;
+use std::{f64::consts::PI, sync::Arc};
 
-use crate::{hittable::{HitRecord, Hittable}, interval::Interval, material::Material, ray::{Point3, Ray}, vec3::{dot, Vec3}};
+use crate::{bsdf::Frame, hittable::{HitRecord, Hittable}, interval::Interval, material::Material, ray::{Point3, Ray}, sampling::sample_uniform_sphere, utils::{gamma, random_float}, vec3::{abs, dot, unit_vector, Vec3}};
 
 pub struct Sphere {
     center: Vec3,
@@ -30,19 +30,85 @@ impl Hittable for Sphere {
             }
         }
 
+        // Reproject the hit point onto the surface; this removes the error of the root and leaves
+        // only the handful of roundings below, which p_error bounds.
+        let mut local = ray.at(root) - self.center;
+        local *= self.radius / local.length();
+        let p = self.center + local;
+        let p_error = gamma(5) * abs(local) + gamma(1) * abs(p);
+
+        let outward_normal = local / self.radius;
+        let (u, v) = Self::get_sphere_uv(&outward_normal);
+
+        // Derivatives of the parameterization in get_sphere_uv; rho vanishes at the poles, where
+        // dpdu is degenerate anyway.
+        let (x, y, z) = (local.x(), local.y(), local.z());
+        let rho = f64::max(f64::sqrt(x * x + z * z), 1e-12 * self.radius);
+        let dpdu = 2.0 * PI * Vec3::with_values(z, 0.0, -x);
+        let dpdv = PI * Vec3::with_values(-x * y / rho, rho, -y * z / rho);
+
         let mut hit_record = HitRecord {
             t: root,
-            p: ray.at(root),
+            u,
+            v,
+            p,
+            p_error,
+            dpdu,
+            dpdv,
+            dndu: dpdu / self.radius,
+            dndv: dpdv / self.radius,
+            footprint: Default::default(),
             mat: self.mat.clone(),
             normal: Default::default(),
+            shading_normal: Default::default(),
             front_face: Default::default(),
+            light: false,
         };
 
-        let outward_normal = (hit_record.p - self.center) / self.radius;
         hit_record.set_face_normal(ray, outward_normal);
 
         Some(hit_record)
     }
+
+    fn occluded(&self, ray: &Ray, ray_t: Interval) -> bool {
+        let oc = self.center - ray.origin();
+        let a = ray.direction().length_squared();
+        let h = dot(&ray.direction(), &oc);
+        let c = oc.length_squared() - (self.radius*self.radius);
+
+        let discriminant = h*h - a*c;
+        if discriminant < 0.0 {
+            return false;
+        }
+
+        let sqrtd = f64::sqrt(discriminant);
+        ray_t.surrounds((h - sqrtd) / a) || ray_t.surrounds((h + sqrtd) / a)
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        if !self.occluded(&Ray::new(origin, direction), Interval::POSITIVE) {
+            return 0.0;
+        }
+        match self.cone(origin) {
+            // 1 - cos θmax, without the cancellation for small, distant spheres.
+            Some((sin2_theta_max, cos_theta_max)) => 1.0 / (2.0 * PI * sin2_theta_max / (1.0 + cos_theta_max)),
+            None => 1.0 / (4.0 * PI),
+        }
+    }
+
+    fn sample(&self, origin: Point3) -> Vec3 {
+        let Some((sin2_theta_max, cos_theta_max)) = self.cone(origin) else {
+            return sample_uniform_sphere((random_float(), random_float()));
+        };
+
+        // Uniform over the cone of directions the sphere subtends.
+        let one_minus_cos = random_float() * sin2_theta_max / (1.0 + cos_theta_max);
+        let cos_theta = 1.0 - one_minus_cos;
+        let sin_theta = f64::sqrt(f64::max(0.0, one_minus_cos * (2.0 - one_minus_cos)));
+        let phi = 2.0 * PI * random_float();
+        let frame = Frame::from_z_and_tangent(unit_vector(&(self.center - origin)), Vec3::new());
+        frame.to_world(Vec3::with_values(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta))
+    }
 }
 
 impl Sphere {
@@ -53,4 +119,24 @@ impl Sphere {
             mat,
         }
     }
+
+    /// sin² and cos of the half-angle of the cone the sphere subtends from `origin`, or `None` from
+    /// inside, where every direction hits it once and uniform sampling is exact.
+    fn cone(&self, origin: Point3) -> Option<(f64, f64)> {
+        let distance_squared = (self.center - origin).length_squared();
+        if distance_squared <= self.radius * self.radius {
+            return None;
+        }
+        let sin2_theta_max = self.radius * self.radius / distance_squared;
+        Some((sin2_theta_max, f64::sqrt(1.0 - sin2_theta_max)))
+    }
+
+    /// Maps a point on the unit sphere to (u, v) in [0, 1]²: u is the angle around the Y axis starting
+    /// from X = -1, v the angle from Y = -1 up to Y = +1.
+    fn get_sphere_uv(p: &Point3) -> (f64, f64) {
+        let theta = f64::acos(-p.y());
+        let phi = f64::atan2(-p.z(), p.x()) + PI;
+
+        (phi / (2.0 * PI), theta / PI)
+    }
 }
@@ -0,0 +1,183 @@
+use std::{path::Path, sync::Arc};
+
+use image::ImageResult;
+
+use crate::{color::Color, hittable::{Footprint, HitRecord}, mipmap::{FilterMode, MipMap, WrapMode}, ray::Point3, texture_cache::TextureCache, vec3::Vec3};
+
+/// Where a texture is evaluated: the surface point, its (u, v), the outward shading normal, and how
+/// much of the surface the current pixel covers, for textures that filter.
+pub struct TextureContext {
+    pub p: Point3,
+    pub u: f64,
+    pub v: f64,
+    /// Zero when the lookup isn't tied to a surface hit.
+    pub normal: Vec3,
+    /// Whether the lookup is on the side of the surface the outward normal points to.
+    pub front_face: bool,
+    pub footprint: Footprint,
+}
+
+impl TextureContext {
+    /// A point lookup, with no footprint to filter over.
+    pub fn new(u: f64, v: f64, p: Point3) -> Self {
+        Self { p, u, v, normal: Vec3::new(), front_face: true, footprint: Footprint::default() }
+    }
+
+    pub fn from_hit(hit_record: &HitRecord) -> Self {
+        Self {
+            p: hit_record.p,
+            u: hit_record.u,
+            v: hit_record.v,
+            normal: hit_record.outward_shading_normal(),
+            front_face: hit_record.front_face,
+            footprint: hit_record.footprint,
+        }
+    }
+}
+
+pub trait Texture: Send + Sync {
+    fn value(&self, ctx: &TextureContext) -> Color;
+
+    /// The texture read as a single number, for scalar parameters: the mean of the three channels,
+    /// which is exact for gray textures.
+    fn scalar(&self, ctx: &TextureContext) -> f64 {
+        let c = self.value(ctx);
+        (c.x() + c.y() + c.z()) / 3.0
+    }
+}
+
+pub struct SolidColor {
+    albedo: Color,
+}
+
+impl SolidColor {
+    pub fn new(albedo: Color) -> Self {
+        Self { albedo }
+    }
+
+    /// The same `value` in every channel, for textures read as scalars.
+    pub fn gray(value: f64) -> Self {
+        Self::new(Color::with_values(value, value, value))
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _ctx: &TextureContext) -> Color {
+        self.albedo
+    }
+}
+
+/// Solid 3D checker: space is cut into cubes of side `scale`, alternating between two textures.
+pub struct CheckerTexture {
+    inv_scale: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        Self {
+            inv_scale: 1.0 / scale,
+            even,
+            odd,
+        }
+    }
+
+    pub fn from_colors(scale: f64, even: Color, odd: Color) -> Self {
+        Self::new(scale, Arc::new(SolidColor::new(even)), Arc::new(SolidColor::new(odd)))
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, ctx: &TextureContext) -> Color {
+        let x = f64::floor(self.inv_scale * ctx.p.x()) as i64;
+        let y = f64::floor(self.inv_scale * ctx.p.y()) as i64;
+        let z = f64::floor(self.inv_scale * ctx.p.z()) as i64;
+
+        let point_sample = if (x + y + z) % 2 == 0 {
+            self.even.value(ctx)
+        } else {
+            self.odd.value(ctx)
+        };
+
+        // Once a pixel spans more than about half a cell, the pattern can only alias; fade it out to
+        // the average of the two textures instead.
+        let footprint = f64::max(ctx.footprint.dpdx.length(), ctx.footprint.dpdy.length()) * self.inv_scale;
+        let fade = (2.0 * footprint - 1.0).clamp(0.0, 1.0);
+        if fade == 0.0 {
+            return point_sample;
+        }
+
+        let average = 0.5 * (self.even.value(ctx) + self.odd.value(ctx));
+        (1.0 - fade) * point_sample + fade * average
+    }
+}
+
+/// Image file texture. Texels are decoded to linear once at load time and kept in a mip
+/// pyramid, so distant and grazing lookups can be filtered instead of aliasing.
+pub struct ImageTexture {
+    mipmap: MipMap,
+}
+
+impl ImageTexture {
+    pub fn load<P: AsRef<Path>>(path: P, wrap: WrapMode, filter: FilterMode) -> ImageResult<Self> {
+        Ok(Self { mipmap: MipMap::load(path, true, wrap, filter)? })
+    }
+
+    /// Loads an image holding data rather than color, such as a normal map, without the sRGB decode.
+    pub fn load_linear<P: AsRef<Path>>(path: P, wrap: WrapMode, filter: FilterMode) -> ImageResult<Self> {
+        Ok(Self { mipmap: MipMap::load(path, false, wrap, filter)? })
+    }
+
+    /// Loads an image's alpha channel as a gray mask, such as the coverage of a cutout.
+    pub fn load_alpha<P: AsRef<Path>>(path: P, wrap: WrapMode, filter: FilterMode) -> ImageResult<Self> {
+        Ok(Self { mipmap: MipMap::load_alpha(path, wrap, filter)? })
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, ctx: &TextureContext) -> Color {
+        let footprint = &ctx.footprint;
+        self.mipmap.filter(ctx.u, ctx.v, footprint.dudx, footprint.dudy, footprint.dvdx, footprint.dvdy)
+    }
+}
+
+/// Texture set split into UDIM tiles: tile 1001 covers (u, v) in [0, 1]², numbers grow by one per unit
+/// of u (up to ten per row) and by ten per unit of v. `pattern` is a file path with `<UDIM>` in place
+/// of the tile number, e.g. `textures/albedo.<UDIM>.png`. Tiles come from a shared `TextureCache`, so
+/// only the ones the render actually touches are ever in memory; missing tiles are black.
+pub struct UdimTexture {
+    cache: Arc<TextureCache>,
+    pattern: u32,
+    srgb: bool,
+    filter: FilterMode,
+}
+
+impl UdimTexture {
+    pub fn new(pattern: &str, cache: Arc<TextureCache>, filter: FilterMode) -> Self {
+        Self { pattern: cache.register(pattern), cache, srgb: true, filter }
+    }
+
+    /// Like `new`, for tiles holding data rather than color (see `ImageTexture::load_linear`).
+    pub fn new_linear(pattern: &str, cache: Arc<TextureCache>, filter: FilterMode) -> Self {
+        Self { pattern: cache.register(pattern), cache, srgb: false, filter }
+    }
+}
+
+impl Texture for UdimTexture {
+    fn value(&self, ctx: &TextureContext) -> Color {
+        let (tile_u, tile_v) = (ctx.u.floor(), ctx.v.floor());
+        // UDIM numbers are four digits: ten tiles per row and rows v = 0 to 99.
+        if !(0.0..10.0).contains(&tile_u) || !(0.0..100.0).contains(&tile_v) {
+            return Color::new();
+        }
+
+        let tile = 1001 + tile_u as u32 + 10 * tile_v as u32;
+        let Some(mipmap) = self.cache.get(self.pattern, tile, self.srgb, self.filter) else {
+            return Color::new();
+        };
+
+        let footprint = &ctx.footprint;
+        mipmap.filter(ctx.u - tile_u, ctx.v - tile_v, footprint.dudx, footprint.dudy, footprint.dvdx, footprint.dvdy)
+    }
+}
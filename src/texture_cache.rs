@@ -0,0 +1,205 @@
+use std::{
+    collections::HashMap,
+    mem::size_of,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use crate::mipmap::{FilterMode, MipMap, WrapMode};
+
+/// Everything that changes the pyramid built from a tile: the registered path pattern, the tile
+/// number substituted into it, and how the file is decoded. Cheap to build and hash, so lookups that
+/// hit don't allocate.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct TileKey {
+    pattern: u32,
+    tile: u32,
+    srgb: bool,
+    filter: FilterMode,
+}
+
+struct Entry {
+    /// `None` records that the file is missing or unreadable, so sparse tile sets don't hit the disk
+    /// again on every lookup.
+    mipmap: Option<Arc<MipMap>>,
+    bytes: usize,
+    last_used: AtomicU64,
+}
+
+/// What a missing tile is charged against the budget, so misses get evicted like everything else.
+const MISSING_TILE_BYTES: usize = size_of::<TileKey>() + size_of::<Entry>();
+
+/// Thread-safe cache of image tiles, loaded on first use and evicted least-recently-used first once
+/// the resident texels exceed `budget_bytes`. Lookups that hit only take a read lock, so the render
+/// workers don't serialize on it.
+pub struct TextureCache {
+    budget_bytes: usize,
+    entries: RwLock<HashMap<TileKey, Entry>>,
+    resident_bytes: AtomicUsize,
+    /// Advances on every lookup, hit or miss, and stamps the entry looked up.
+    clock: AtomicU64,
+    /// Registered path patterns, indexed by `TileKey::pattern`. Only read on a miss.
+    patterns: RwLock<Vec<String>>,
+}
+
+impl TextureCache {
+    pub fn new(budget_bytes: usize) -> Self {
+        Self {
+            budget_bytes,
+            entries: RwLock::new(HashMap::new()),
+            resident_bytes: AtomicUsize::new(0),
+            clock: AtomicU64::new(0),
+            patterns: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Id of a file path pattern with `<UDIM>` in place of the tile number. Registering the same
+    /// pattern twice gives the same id, so textures reading the same files share their tiles.
+    pub fn register(&self, pattern: &str) -> u32 {
+        let mut patterns = self.patterns.write().unwrap();
+        let id = match patterns.iter().position(|known| known == pattern) {
+            Some(id) => id,
+            None => {
+                patterns.push(pattern.to_string());
+                patterns.len() - 1
+            }
+        };
+        id as u32
+    }
+
+    /// The pyramid for `tile` of a registered `pattern`, loading it (and evicting others) if it isn't
+    /// resident. Returns `None` for files that don't exist or fail to decode.
+    pub fn get(&self, pattern: u32, tile: u32, srgb: bool, filter: FilterMode) -> Option<Arc<MipMap>> {
+        let key = TileKey { pattern, tile, srgb, filter };
+        let now = self.clock.fetch_add(1, Ordering::Relaxed);
+
+        if let Some(entry) = self.entries.read().unwrap().get(&key) {
+            entry.last_used.store(now, Ordering::Relaxed);
+            return entry.mipmap.clone();
+        }
+
+        // Decode without holding the lock; if another worker raced us to the same tile, keep theirs.
+        // Tiles don't wrap into themselves, the neighbouring tile is a different image.
+        let path = self.patterns.read().unwrap()[pattern as usize].replace("<UDIM>", &tile.to_string());
+        let mipmap = MipMap::load(path, srgb, WrapMode::Clamp, filter).ok().map(Arc::new);
+        let bytes = mipmap.as_ref().map_or(MISSING_TILE_BYTES, |mipmap| mipmap.memory_size());
+
+        let mut entries = self.entries.write().unwrap();
+        if let Some(entry) = entries.get(&key) {
+            entry.last_used.store(now, Ordering::Relaxed);
+            return entry.mipmap.clone();
+        }
+
+        self.evict(&mut entries, bytes);
+        self.resident_bytes.fetch_add(bytes, Ordering::Relaxed);
+        entries.insert(key, Entry { mipmap: mipmap.clone(), bytes, last_used: AtomicU64::new(now) });
+        mipmap
+    }
+
+    /// Once `incoming` more bytes would overflow the budget, drops least recently used tiles until
+    /// the cache is down to 7/8 of it, so the sort is paid once per batch rather than per miss. A
+    /// tile larger than the whole budget is still admitted once everything else is gone.
+    fn evict(&self, entries: &mut HashMap<TileKey, Entry>, incoming: usize) {
+        if self.resident_bytes.load(Ordering::Relaxed) + incoming <= self.budget_bytes {
+            return;
+        }
+
+        let mut by_age: Vec<_> = entries.iter().map(|(key, entry)| (entry.last_used.load(Ordering::Relaxed), *key)).collect();
+        by_age.sort_unstable_by_key(|&(last_used, _)| last_used);
+
+        let low_water = self.budget_bytes / 8 * 7;
+        for (_, key) in by_age {
+            if self.resident_bytes.load(Ordering::Relaxed) + incoming <= low_water {
+                return;
+            }
+            if let Some(entry) = entries.remove(&key) {
+                self.resident_bytes.fetch_sub(entry.bytes, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+
+    /// A directory of 4x4 tiles 1001, 1002, ... for `pattern`, one per color.
+    fn tiles(name: &str, count: u32) -> (PathBuf, String) {
+        let dir = std::env::temp_dir().join(format!("texture_cache_{name}_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for i in 0..count {
+            RgbImage::from_pixel(4, 4, Rgb([10 * i as u8, 0, 0])).save(dir.join(format!("tile.{}.png", 1001 + i))).unwrap();
+        }
+        let pattern = dir.join("tile.<UDIM>.png").to_str().unwrap().to_string();
+        (dir, pattern)
+    }
+
+    /// Bytes one of the 4x4 tiles takes once resident.
+    fn tile_bytes(pattern: &str) -> usize {
+        MipMap::load(pattern.replace("<UDIM>", "1001"), true, WrapMode::Clamp, FilterMode::Bilinear).unwrap().memory_size()
+    }
+
+    #[test]
+    fn hits_share_the_loaded_tile() {
+        let (dir, pattern) = tiles("hits", 1);
+        let cache = TextureCache::new(1 << 20);
+        let id = cache.register(&pattern);
+        assert_eq!(cache.register(&pattern), id);
+
+        let first = cache.get(id, 1001, true, FilterMode::Bilinear).unwrap();
+        let second = cache.get(id, 1001, true, FilterMode::Bilinear).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(cache.resident_bytes.load(Ordering::Relaxed), first.memory_size());
+
+        // Decoding differently is a different pyramid.
+        let linear = cache.get(id, 1001, false, FilterMode::Bilinear).unwrap();
+        assert!(!Arc::ptr_eq(&first, &linear));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn misses_are_remembered_and_charged() {
+        let (dir, pattern) = tiles("misses", 1);
+        let cache = TextureCache::new(1 << 20);
+        let id = cache.register(&pattern);
+
+        assert!(cache.get(id, 1002, true, FilterMode::Bilinear).is_none());
+        assert_eq!(cache.resident_bytes.load(Ordering::Relaxed), MISSING_TILE_BYTES);
+
+        // Once recorded, the miss isn't looked up on disk again even if the file appears.
+        RgbImage::new(4, 4).save(dir.join("tile.1002.png")).unwrap();
+        assert!(cache.get(id, 1002, true, FilterMode::Bilinear).is_none());
+        assert_eq!(cache.entries.read().unwrap().len(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn eviction_keeps_recently_used_tiles_within_budget() {
+        let (dir, pattern) = tiles("eviction", 4);
+        let bytes = tile_bytes(&pattern);
+        let cache = TextureCache::new(3 * bytes);
+        let id = cache.register(&pattern);
+        let get = |tile| cache.get(id, tile, true, FilterMode::Bilinear).unwrap();
+
+        let first = get(1001);
+        let second = get(1002);
+        get(1003);
+        assert_eq!(cache.resident_bytes.load(Ordering::Relaxed), 3 * bytes);
+
+        // A hit makes 1001 the most recent, so the fourth tile pushes out 1002 and then 1003 to get
+        // down to the low-water mark.
+        assert!(Arc::ptr_eq(&first, &get(1001)));
+        get(1004);
+        assert!(cache.resident_bytes.load(Ordering::Relaxed) <= 3 * bytes / 8 * 7);
+        assert!(Arc::ptr_eq(&first, &get(1001)));
+        assert!(!Arc::ptr_eq(&second, &get(1002)));
+        assert!(cache.resident_bytes.load(Ordering::Relaxed) <= 3 * bytes);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use crate::{hittable::{HitRecord, Hittable}, interval::Interval, material::Material, ray::{Point3, Ray}, utils::{gamma, random_float}, vec3::{abs, cross, dot, unit_vector, Vec3}};
+
+/// Single triangle, the building block of meshes. Vertices carry (u, v) coordinates, which also
+/// define the tangents `dpdu`/`dpdv` that normal and bump maps are oriented by.
+pub struct Triangle {
+    vertices: [Point3; 3],
+    uvs: [(f64, f64); 3],
+    normal: Vec3,
+    mat: Arc<dyn Material>,
+}
+
+impl Triangle {
+    /// Triangle with the default parameterization (0, 0), (1, 0), (1, 1) over its vertices.
+    pub fn new(v0: Point3, v1: Point3, v2: Point3, mat: Arc<dyn Material>) -> Self {
+        Self::with_uvs([v0, v1, v2], [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)], mat)
+    }
+
+    pub fn with_uvs(vertices: [Point3; 3], uvs: [(f64, f64); 3], mat: Arc<dyn Material>) -> Self {
+        let normal = unit_vector(&cross(&(vertices[1] - vertices[0]), &(vertices[2] - vertices[0])));
+        Triangle { vertices, uvs, normal, mat }
+    }
+
+    /// Möller-Trumbore intersection; returns `t` and the barycentric weights of the hit.
+    fn intersect(&self, ray: &Ray, ray_t: Interval) -> Option<(f64, [f64; 3])> {
+        let [p0, p1, p2] = self.vertices;
+        let edge1 = p1 - p0;
+        let edge2 = p2 - p0;
+
+        let pvec = cross(&ray.direction(), &edge2);
+        let det = dot(&edge1, &pvec);
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+
+        let tvec = ray.origin() - p0;
+        let b1 = dot(&tvec, &pvec) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+
+        let qvec = cross(&tvec, &edge1);
+        let b2 = dot(&ray.direction(), &qvec) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+
+        let t = dot(&edge2, &qvec) * inv_det;
+        if !ray_t.surrounds(t) {
+            return None;
+        }
+
+        Some((t, [1.0 - b1 - b2, b1, b2]))
+    }
+
+    /// Tangents from the (u, v) mapping, solving the 2x2 system of pbrt section 6.5.3; degenerate
+    /// mappings fall back to an arbitrary frame around the normal.
+    fn partial_derivatives(&self) -> (Vec3, Vec3) {
+        let [p0, p1, p2] = self.vertices;
+        let [uv0, uv1, uv2] = self.uvs;
+        let duv02 = (uv0.0 - uv2.0, uv0.1 - uv2.1);
+        let duv12 = (uv1.0 - uv2.0, uv1.1 - uv2.1);
+        let dp02 = p0 - p2;
+        let dp12 = p1 - p2;
+
+        let determinant = duv02.0 * duv12.1 - duv02.1 * duv12.0;
+        if determinant.abs() < 1e-12 {
+            let helper = if self.normal.x().abs() > 0.9 { Vec3::with_values(0.0, 1.0, 0.0) } else { Vec3::with_values(1.0, 0.0, 0.0) };
+            let dpdu = unit_vector(&cross(&helper, &self.normal));
+            return (dpdu, cross(&self.normal, &dpdu));
+        }
+
+        let inv_det = 1.0 / determinant;
+        let dpdu = (duv12.1 * dp02 - duv02.1 * dp12) * inv_det;
+        let dpdv = (duv02.0 * dp12 - duv12.0 * dp02) * inv_det;
+        (dpdu, dpdv)
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let (t, b) = self.intersect(ray, ray_t)?;
+        let [p0, p1, p2] = self.vertices;
+        let [uv0, uv1, uv2] = self.uvs;
+
+        // As for quads, interpolating the vertices bounds the error by the vertex magnitudes instead of
+        // by the distance travelled along the ray.
+        let p = b[0] * p0 + b[1] * p1 + b[2] * p2;
+        let p_error = gamma(7) * (abs(b[0] * p0) + abs(b[1] * p1) + abs(b[2] * p2));
+        let (dpdu, dpdv) = self.partial_derivatives();
+
+        let mut hit_record = HitRecord {
+            t,
+            u: b[0] * uv0.0 + b[1] * uv1.0 + b[2] * uv2.0,
+            v: b[0] * uv0.1 + b[1] * uv1.1 + b[2] * uv2.1,
+            p,
+            p_error,
+            dpdu,
+            dpdv,
+            dndu: Vec3::new(),
+            dndv: Vec3::new(),
+            footprint: Default::default(),
+            mat: self.mat.clone(),
+            normal: Default::default(),
+            shading_normal: Default::default(),
+            front_face: Default::default(),
+            light: false,
+        };
+
+        hit_record.set_face_normal(ray, self.normal);
+
+        Some(hit_record)
+    }
+
+    fn occluded(&self, ray: &Ray, ray_t: Interval) -> bool {
+        self.intersect(ray, ray_t).is_some()
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let Some((t, _)) = self.intersect(&Ray::new(origin, direction), Interval::POSITIVE) else {
+            return 0.0;
+        };
+        let [p0, p1, p2] = self.vertices;
+        let area = 0.5 * cross(&(p1 - p0), &(p2 - p0)).length();
+        // Uniform by area, converted to solid angle.
+        let distance_squared = t * t * direction.length_squared();
+        let cosine = dot(&direction, &self.normal).abs() / direction.length();
+        distance_squared / (cosine * area)
+    }
+
+    fn sample(&self, origin: Point3) -> Vec3 {
+        // Uniform barycentrics (pbrt, section 6.5.4, in its original square-root form).
+        let [p0, p1, p2] = self.vertices;
+        let su0 = random_float().sqrt();
+        let (b0, b1) = (1.0 - su0, random_float() * su0);
+        b0 * p0 + b1 * p1 + (1.0 - b0 - b1) * p2 - origin
+    }
+}
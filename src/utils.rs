@@ -15,3 +15,10 @@ pub fn random_float_range(min: f64, max: f64) -> f64 {
     let mut rng = rand::thread_rng();
     rng.gen_range(min..max)
 }
+
+/// Conservative bound on the relative rounding error accumulated by `n` floating-point operations,
+/// as defined in pbrt (section 6.8.1).
+pub fn gamma(n: i32) -> f64 {
+    let n_eps = n as f64 * f64::EPSILON * 0.5;
+    n_eps / (1.0 - n_eps)
+}
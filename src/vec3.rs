@@ -3,7 +3,7 @@ use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign,
 
 use crate::utils::{random_float, random_float_range};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Vec3 {
     components: [f64; 3],
 }
@@ -231,20 +231,6 @@ pub fn unit_vector(v: &Vec3) -> Vec3 {
     v / v.length()
 }
 
-pub fn random_unit_vector() -> Vec3 {
-    loop {
-        let p = Vec3::random_range(-1.0, 1.0);
-        let lensq = p.length_squared();
-        //  Sadly, we have a small floating-point abstraction leak to deal with.
-        //  Since floating-point numbers have finite precision, a very small value can underflow to zero when squared.
-        //  So if all three coordinates are small enough (that is, very near the center of the sphere),
-        //  the norm of the vector will be zero, and thus normalizing will yield the bogus vector [±∞,±∞,±∞]
-        if 1e-160 < lensq && lensq <= 1.0 {
-            return p / f64::sqrt(lensq);
-        }
-    }
-}
-
 pub fn reflect(v: Vec3, n: Vec3) -> Vec3 {
     v - 2.0 * dot(&v, &n) * n
 }
@@ -271,6 +257,10 @@ pub fn dot(u: &Vec3, v: &Vec3) -> f64 {
         + u.components[2] * v.components[2]
 }
 
+pub fn abs(v: Vec3) -> Vec3 {
+    Vec3::with_values(v.x().abs(), v.y().abs(), v.z().abs())
+}
+
 pub fn cross(u: &Vec3, v: &Vec3) -> Vec3 {
     Vec3 {
         components: [